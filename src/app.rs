@@ -1,73 +1,100 @@
+use std::{fs, path::PathBuf};
+
 use termion::{
     event::Key,
 };
 
-use super::game::{Game, Rule, Coord};
-use super::piece::{Team, Piece};
+use tafl::{Coord, Game, Piece, Record};
 
 
 pub struct App {
     pub title: String,
     pub game: Game,
+    /// The tags the game is saved with.
+    pub record: Record,
+    pub save_path: PathBuf,
     pub cursor: Coord,
     pub selected: Option<Coord>,
     pub help: bool,
+    pub message: Option<String>,
+    pub quit: bool,
 }
 
 
 impl App {
-    pub fn new(title: String, rule: Rule) -> Self {
+    pub fn new(title: String, game: Game, record: Record, save_path: PathBuf) -> Self {
         App {
             title,
-            game: Game::new(rule),
+            game,
+            record,
+            save_path,
             cursor: (0, 0),
             selected: None,
             help: false,
+            message: None,
+            quit: false,
         }
     }
 
     pub fn on_key(&mut self, key: Key) {
         match key {
             Key::Ctrl('c') | Key::Char('q') => {
-                self.game.winner = Some(Team::Swedes);
+                self.quit = true;
             },
             Key::Char('h') => {
                 self.help ^= true;
             },
-            Key::Left => {
-                if self.cursor.0 > 0 {
-                    self.cursor.0 -= 1;
-                }
+            Key::Char('u') => {
+                self.game.undo();
+                self.selected = self.game.continuing();
+                self.message = None;
             },
-            Key::Up => {
-                if self.cursor.1 > 0 {
-                    self.cursor.1 -= 1;
-                }
+            Key::Char('r') => {
+                self.game.redo();
+                self.selected = self.game.continuing();
+                self.message = None;
             },
-            Key::Right => {
-                if self.cursor.0 < (self.game.board.size - 1) as usize {
-                    self.cursor.0 += 1;
-                }
+            Key::Char('s') => {
+                self.message = Some(match fs::write(&self.save_path, self.record.write(&self.game)) {
+                    Ok(()) => format!("Saved the game to {}.", self.save_path.display()),
+                    Err(err) => format!("Could not save the game: {}.", err),
+                });
             },
-            Key::Down => {
-                if self.cursor.1 < (self.game.board.size - 1) as usize {
-                    self.cursor.1 += 1;
-                }
+            Key::Char('e') => {
+                self.game.end_turn();
+                self.selected = None;
+                self.message = None;
+            },
+            Key::Left if self.cursor.0 > 0 => {
+                self.cursor.0 -= 1;
+            },
+            Key::Up if self.cursor.1 > 0 => {
+                self.cursor.1 -= 1;
+            },
+            Key::Right if self.cursor.0 < (self.game.board.size - 1) as usize => {
+                self.cursor.0 += 1;
+            },
+            Key::Down if self.cursor.1 < (self.game.board.size - 1) as usize => {
+                self.cursor.1 += 1;
             },
             Key::Char(' ') => {
                 match self.selected {
                     Some(selected) => {
-                        self.game.move_piece(selected, self.cursor);
-                        self.selected = None;
+                        self.message = match self.game.move_piece(selected, self.cursor) {
+                            Ok(outcome) if ! outcome.ends_turn => {
+                                Some("Move the piece again or end the turn with e.".to_string())
+                            },
+                            Ok(_) => None,
+                            Err(err) => Some(format!("Illegal move: {}.", err)),
+                        };
+                        self.selected = self.game.continuing();
                     },
                     None => {
-                        match self.game.board.get_piece_at(self.cursor) {
-                            Some(piece) => {
-                                if self.check_turn(piece) {
-                                    self.selected = Some((self.cursor.0, self.cursor.1))
-                                }
-                            },
-                            None => {},  // Nothing
+                        let continuing = self.game.continuing().is_none_or(|at| at == self.cursor);
+                        if let Some(piece) = self.game.board.get_piece_at(self.cursor) {
+                            if self.check_turn(piece) && continuing {
+                                self.selected = Some((self.cursor.0, self.cursor.1))
+                            }
                         }
                     },
                 }
@@ -76,13 +103,7 @@ impl App {
         }
     }
 
-    fn check_turn(&mut self, piece: Piece) -> bool {
-        let team = piece.get_team();
-        let turn = self.game.turn;
-
-        let muscovites = ((turn % 2) == 0) && team == Team::Muscovites;
-        let swedes = ((turn % 2) != 0) && team == Team::Swedes;
-
-        return muscovites || swedes;
+    fn check_turn(&self, piece: Piece) -> bool {
+        piece.get_team() == self.game.side_to_move()
     }
 }
@@ -0,0 +1,550 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+use super::board::{Board, Status};
+use super::game::{Coord, Move};
+use super::piece::{Piece, Team};
+use super::rules::{Escape, RuleSet};
+
+
+const WORDS: usize = 3;
+
+
+/// A set of squares, one bit per square, wide enough for a 19x19 board.
+///
+/// Square `(x, y)` of a board of size `n` is bit `y * n + x`, so shifting by
+/// one moves a set sideways and shifting by `n` moves it up or down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bits([u128; WORDS]);
+
+impl Bits {
+    pub const EMPTY: Bits = Bits([0; WORDS]);
+
+    /// The set containing only bit `i`.
+    pub fn single(i: usize) -> Self {
+        let mut bits = Bits::EMPTY;
+        bits.set(i);
+        bits
+    }
+
+    pub fn set(&mut self, i: usize) {
+        self.0[i / 128] |= 1 << (i % 128);
+    }
+
+    pub fn clear(&mut self, i: usize) {
+        self.0[i / 128] &= !(1 << (i % 128));
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.0[i / 128] & (1 << (i % 128)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&w| w == 0)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Indices of all set bits in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let words = self.0;
+        (0..WORDS).flat_map(move |n| {
+            let mut w = words[n];
+            std::iter::from_fn(move || {
+                if w == 0 {
+                    return None;
+                }
+                let i = w.trailing_zeros() as usize;
+                w &= w - 1;
+                Some(n * 128 + i)
+            })
+        })
+    }
+}
+
+impl Shl<usize> for Bits {
+    type Output = Bits;
+
+    fn shl(self, n: usize) -> Bits {
+        debug_assert!(n < 128);
+        if n == 0 {
+            return self;
+        }
+        let w = self.0;
+        Bits([
+            w[0] << n,
+            (w[1] << n) | (w[0] >> (128 - n)),
+            (w[2] << n) | (w[1] >> (128 - n)),
+        ])
+    }
+}
+
+impl Shr<usize> for Bits {
+    type Output = Bits;
+
+    fn shr(self, n: usize) -> Bits {
+        debug_assert!(n < 128);
+        if n == 0 {
+            return self;
+        }
+        let w = self.0;
+        Bits([
+            (w[0] >> n) | (w[1] << (128 - n)),
+            (w[1] >> n) | (w[2] << (128 - n)),
+            w[2] >> n,
+        ])
+    }
+}
+
+impl BitAnd for Bits {
+    type Output = Bits;
+
+    fn bitand(mut self, rhs: Bits) -> Bits {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for Bits {
+    fn bitand_assign(&mut self, rhs: Bits) {
+        for i in 0..WORDS {
+            self.0[i] &= rhs.0[i];
+        }
+    }
+}
+
+impl BitOr for Bits {
+    type Output = Bits;
+
+    fn bitor(mut self, rhs: Bits) -> Bits {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for Bits {
+    fn bitor_assign(&mut self, rhs: Bits) {
+        for i in 0..WORDS {
+            self.0[i] |= rhs.0[i];
+        }
+    }
+}
+
+impl BitXor for Bits {
+    type Output = Bits;
+
+    fn bitxor(mut self, rhs: Bits) -> Bits {
+        self ^= rhs;
+        self
+    }
+}
+
+impl BitXorAssign for Bits {
+    fn bitxor_assign(&mut self, rhs: Bits) {
+        for i in 0..WORDS {
+            self.0[i] ^= rhs.0[i];
+        }
+    }
+}
+
+impl Not for Bits {
+    type Output = Bits;
+
+    /// The complement over all 384 bits. Mask the result with the squares of
+    /// a board before using it.
+    fn not(self) -> Bits {
+        Bits(self.0.map(|w| !w))
+    }
+}
+
+
+#[derive(Clone, Copy)]
+enum Direction {
+    West,
+    East,
+    North,
+    South,
+}
+
+const DIRECTIONS: [Direction; 4] = [Direction::West, Direction::East, Direction::North, Direction::South];
+
+
+/// A board representation built from bitboards, meant for search and
+/// self-play where `Board` is too slow.
+///
+/// It follows the same movement and custodial capture rules as `Game`,
+/// including the options of its `RuleSet`. Left to `Game` are:
+///
+/// - shieldwalls, berserk turns and camps,
+/// - kinds of pieces besides kings and plain warriors, which are treated as
+///   warriors of their team,
+/// - the game endings besides capturing the king and its escape.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitBoard {
+    size: usize,
+    pub attackers: Bits,
+    pub defenders: Bits,
+    pub king: Bits,
+    /// The throne and the corners, which only the king may enter.
+    pub special: Bits,
+    throne: Bits,
+    throne_neighbours: Bits,
+    full: Bits,
+    edge: Bits,
+    not_west_edge: Bits,
+    not_east_edge: Bits,
+    rules: RuleSet,
+}
+
+impl BitBoard {
+    /// Convert `board` into its bitboard representation, to be played with
+    /// `rules`.
+    pub fn from_board(board: &Board, rules: RuleSet) -> Self {
+        let size = board.size as usize;
+        assert!(size * size <= WORDS * 128, "board too large for a bitboard");
+
+        let mut bitboard = Self {
+            size,
+            attackers: Bits::EMPTY,
+            defenders: Bits::EMPTY,
+            king: Bits::EMPTY,
+            special: Bits::EMPTY,
+            throne: Bits::EMPTY,
+            throne_neighbours: Bits::EMPTY,
+            full: Bits::EMPTY,
+            edge: Bits::EMPTY,
+            not_west_edge: Bits::EMPTY,
+            not_east_edge: Bits::EMPTY,
+            rules,
+        };
+
+        for x in 0..size {
+            for y in 0..size {
+                let i = bitboard.index((x, y));
+                bitboard.full.set(i);
+                if x != 0 { bitboard.not_west_edge.set(i); }
+                if x != size - 1 { bitboard.not_east_edge.set(i); }
+                if x == 0 || y == 0 || x == size - 1 || y == size - 1 { bitboard.edge.set(i); }
+
+                match board.get_status_at((x, y)) {
+                    Some(Status::Castle) => {
+                        bitboard.special.set(i);
+                        bitboard.throne.set(i);
+                    },
+                    Some(Status::Corner) => bitboard.special.set(i),
+                    Some(Status::Camp) | None => {},
+                }
+
+                match board.get_piece_at((x, y)) {
+                    Some(Piece::King) => bitboard.king.set(i),
+                    Some(piece) if piece.get_team() == Team::Muscovites => bitboard.attackers.set(i),
+                    Some(_) => bitboard.defenders.set(i),
+                    None => {},
+                }
+            }
+        }
+
+        bitboard.throne_neighbours = DIRECTIONS
+            .iter()
+            .fold(Bits::EMPTY, |bits, &direction| bits | bitboard.shift(bitboard.throne, direction));
+
+        bitboard
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn index(&self, at: Coord) -> usize {
+        at.1 * self.size + at.0
+    }
+
+    pub fn coord(&self, i: usize) -> Coord {
+        (i % self.size, i / self.size)
+    }
+
+    pub fn occupied(&self) -> Bits {
+        self.attackers | self.defenders | self.king
+    }
+
+    /// All pieces of `team`, including the king for the Swedes.
+    pub fn team(&self, team: Team) -> Bits {
+        match team {
+            Team::Muscovites => self.attackers,
+            Team::Swedes => self.defenders | self.king,
+        }
+    }
+
+    pub fn piece_at(&self, at: Coord) -> Option<Piece> {
+        let i = self.index(at);
+        if self.king.contains(i) {
+            Some(Piece::King)
+        } else if self.attackers.contains(i) {
+            Some(Piece::Muscovite)
+        } else if self.defenders.contains(i) {
+            Some(Piece::Swede)
+        } else {
+            None
+        }
+    }
+
+    fn shift(&self, bits: Bits, direction: Direction) -> Bits {
+        match direction {
+            Direction::West => (bits >> 1) & self.not_east_edge,
+            Direction::East => (bits << 1) & self.not_west_edge,
+            Direction::North => bits >> self.size,
+            Direction::South => (bits << self.size) & self.full,
+        }
+    }
+
+    /// All squares the piece at bit `i` can move to.
+    pub fn targets(&self, i: usize) -> Bits {
+        let from = Bits::single(i);
+        let is_king = self.king.contains(i);
+        let mut empty = self.full & !self.occupied();
+        if !is_king && !self.rules.throne_passable {
+            empty &= !self.throne;
+        }
+
+        let mut targets = Bits::EMPTY;
+        for direction in DIRECTIONS {
+            let mut ray = self.shift(from, direction) & empty;
+            while !ray.is_empty() {
+                targets |= ray;
+                ray = self.shift(ray, direction) & empty;
+            }
+        }
+
+        if is_king {
+            targets
+        } else {
+            targets & !self.special
+        }
+    }
+
+    /// All legal moves of `team`.
+    pub fn legal_moves(&self, team: Team) -> Vec<Move> {
+        if self.winner().is_some() {
+            return Vec::new();
+        }
+
+        let mut moves = Vec::new();
+        for i in self.team(team).iter() {
+            let from = self.coord(i);
+            for j in self.targets(i).iter() {
+                moves.push(Move { from, to: self.coord(j) });
+            }
+        }
+        moves
+    }
+
+    /// Make `mv` without checking whether it is legal and return the squares
+    /// of all pieces it captured.
+    pub fn make_move(&mut self, mv: Move) -> Bits {
+        let from = Bits::single(self.index(mv.from));
+        let to = Bits::single(self.index(mv.to));
+        let both = from | to;
+
+        let team = if !(self.attackers & from).is_empty() {
+            self.attackers ^= both;
+            Team::Muscovites
+        } else if !(self.defenders & from).is_empty() {
+            self.defenders ^= both;
+            Team::Swedes
+        } else if !(self.king & from).is_empty() {
+            self.king ^= both;
+            if !self.rules.king_armed {
+                return Bits::EMPTY;
+            }
+            Team::Swedes
+        } else {
+            return Bits::EMPTY;
+        };
+
+        let captured = self.captures(team, to);
+        self.attackers &= !captured;
+        self.defenders &= !captured;
+        self.king &= !captured;
+        captured
+    }
+
+    /// Pieces captured by a piece of `team` that just arrived at `to`.
+    fn captures(&self, team: Team, to: Bits) -> Bits {
+        let enemy = self.team(team.opponent());
+        let anvil = self.hostile_to(team.opponent());
+
+        let mut captured = Bits::EMPTY;
+        for direction in DIRECTIONS {
+            let victim = self.shift(to, direction) & enemy;
+            if victim.is_empty() {
+                continue;
+            }
+
+            let killed = if (victim & self.king).is_empty() {
+                !(self.shift(victim, direction) & anvil).is_empty()
+            } else {
+                self.king_captured(direction)
+            };
+
+            if killed {
+                captured |= victim;
+            }
+        }
+        captured
+    }
+
+    /// Whether the king is captured by a Muscovite that moved next to it
+    /// against `direction`.
+    fn king_captured(&self, direction: Direction) -> bool {
+        let anvil = self.hostile_to(Team::Swedes);
+        let near_throne = !(self.king & (self.throne | self.throne_neighbours)).is_empty();
+        let sides = if self.rules.king_strong_on_throne && near_throne {
+            4
+        } else {
+            self.rules.king_capture_sides
+        };
+
+        if sides <= 2 {
+            !(self.shift(self.king, direction) & anvil).is_empty()
+        } else {
+            let hostile = DIRECTIONS
+                .iter()
+                .filter(|&&d| !(self.shift(self.king, d) & anvil).is_empty())
+                .count();
+            hostile >= sides as usize
+        }
+    }
+
+    /// All squares that help capturing a piece of `team`: enemy pieces and
+    /// hostile squares.
+    fn hostile_to(&self, team: Team) -> Bits {
+        let empty = self.full & !self.occupied();
+        let corners = self.special & !self.throne;
+
+        let mut hostile = match team {
+            Team::Muscovites if self.rules.king_armed => self.defenders | self.king,
+            Team::Muscovites => self.defenders,
+            Team::Swedes => self.attackers,
+        };
+        hostile |= corners & empty;
+
+        let occupied = !(self.throne & self.occupied()).is_empty();
+        if self.rules.throne_hostile_to(team, occupied) {
+            hostile |= self.throne;
+        }
+        hostile
+    }
+
+    /// The winner of the position, if the king has been captured or has
+    /// escaped.
+    pub fn winner(&self) -> Option<Team> {
+        let escape = match self.rules.escape {
+            Escape::Corner => self.special & !self.throne,
+            Escape::Edge => self.edge,
+        };
+
+        if self.king.is_empty() {
+            Some(Team::Muscovites)
+        } else if !(self.king & escape).is_empty() {
+            Some(Team::Swedes)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_bits_shift_across_words() -> std::io::Result<()> {
+    let bits = Bits::single(127);
+    assert_eq!(bits << 1, Bits::single(128), "shift left lost a bit");
+    assert_eq!((bits << 20) >> 20, bits, "shift right lost a bit");
+    assert_eq!(Bits::single(300).iter().collect::<Vec<_>>(), vec![300], "iter skipped a bit");
+    Ok(())
+}
+
+#[test]
+fn test_from_board() -> std::io::Result<()> {
+    let rule = super::game::Rule::Hnefatafl11;
+    let bitboard = BitBoard::from_board(&Board::new(rule), rule.rules());
+    assert_eq!(bitboard.attackers.count(), 24, "wrong number of attackers");
+    assert_eq!(bitboard.defenders.count(), 12, "wrong number of defenders");
+    assert_eq!(bitboard.piece_at((5, 5)), Some(Piece::King), "king is not on the throne");
+    assert_eq!(bitboard.special.count(), 5, "wrong number of special squares");
+    Ok(())
+}
+
+#[test]
+fn test_matches_game() -> std::io::Result<()> {
+    use super::game::{Game, Rule};
+
+    // xorshift, so the games are random but reproducible
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    let strict = RuleSet {
+        king_armed: false,
+        king_capture_sides: 3,
+        king_strong_on_throne: false,
+        escape: Escape::Edge,
+        throne_hostile_to_muscovites: false,
+        throne_passable: false,
+        ..RuleSet::default()
+    };
+    let variants = [
+        (Rule::Tablut, Rule::Tablut.rules()),
+        (Rule::Hnefatafl11, Rule::Hnefatafl11.rules()),
+        (Rule::Hnefatafl13, Rule::Hnefatafl13.rules()),
+        (Rule::LinnaeusTablut, Rule::LinnaeusTablut.rules()),
+        (Rule::Brandubh, Rule::Brandubh.rules()),
+        (Rule::ArdRi, Rule::ArdRi.rules()),
+        (Rule::AleaEvangelii, Rule::AleaEvangelii.rules()),
+        (Rule::Tawlbwrdd, Rule::Tawlbwrdd.rules()),
+        (Rule::Copenhagen, Rule::Copenhagen.rules()),
+        (Rule::Fetlar, Rule::Fetlar.rules()),
+        (Rule::Hnefatafl11, strict),
+    ];
+
+    for (rule, rules) in variants {
+        // shieldwalls are left to `Game`, so they must not decide captures here
+        let rules = RuleSet { shieldwall: false, ..rules };
+        for _ in 0..5 {
+            let mut game = Game::with_rules(rule, rules);
+            let mut bitboard = BitBoard::from_board(&game.board, game.rules);
+
+            for _ in 0..300 {
+                let mut expected = game.legal_moves();
+                let mut moves = bitboard.legal_moves(game.side_to_move());
+                expected.sort_by_key(|m| (m.from, m.to));
+                moves.sort_by_key(|m| (m.from, m.to));
+                assert_eq!(moves, expected, "legal moves differ");
+
+                if moves.is_empty() {
+                    break;
+                }
+
+                let mv = moves[(random() % moves.len() as u64) as usize];
+                let outcome = game.move_piece(mv.from, mv.to).unwrap();
+                let captured = bitboard.make_move(mv);
+
+                let mut expected = outcome.captured.clone();
+                expected.sort_by_key(|&(x, y)| (y, x));
+                let captured = captured.iter().map(|i| bitboard.coord(i)).collect::<Vec<_>>();
+                assert_eq!(captured, expected, "captures differ after {:?}", mv);
+                assert_eq!(bitboard, BitBoard::from_board(&game.board, game.rules), "boards differ after {:?}", mv);
+
+                // repetitions, encirclement, forts and blocked sides are left to `Game`
+                if game.result.is_some() && bitboard.winner().is_none() {
+                    break;
+                }
+                assert_eq!(bitboard.winner(), game.winner(), "winner differs after {:?}", mv);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,27 @@
 use super::game::{
-    SIZE_HNEFATAFL_11,
-    SIZE_HNEFATAFL_13,
-    SIZE_TABLUT,
     Rule,
     Coord
 };
 
 use super::piece::{Piece};
+use super::variant::{self, VariantError};
+use super::zobrist;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// The four directions pieces move and capture in, as `(dx, dy)`.
+pub const DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// Special squares of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Status {
     Corner,
     Castle,
+    /// A camp (or citadel) of the Muscovites. Pieces starting in a camp may
+    /// move within it and leave it, no piece may enter it from outside.
+    Camp,
 }
 
-#[derive(Clone, Copy)]
+/// A single square of the board, possibly occupied by a piece.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Square {
     pub piece: Option<Piece>,
     pub status: Option<Status>,
@@ -31,159 +38,305 @@ impl Square {
 }
 
 
+/// The board of a game, indexed as `board[column][row]`.
+#[derive(Clone)]
 pub struct Board {
     pub board: Vec<Vec<Square>>,
     pub size: u16,
-    pub rule: Rule,
+    hash: u64,
 }
 
 
 impl Board {
+    /// Set up a board with the initial layout of `rule`.
     pub fn new(rule: Rule) -> Self {
-        let size = match rule {
-            Rule::Hnefatafl11 => SIZE_HNEFATAFL_11,
-            Rule::Hnefatafl13 => SIZE_HNEFATAFL_13,
-            Rule::Tablut => SIZE_TABLUT,
+        rule.variant().board
+    }
+
+    /// A board with the given squares, indexed as `squares[column][row]`.
+    pub(crate) fn from_squares(squares: Vec<Vec<Square>>) -> Self {
+        let mut board = Self {
+            size: squares.len() as u16,
+            board: squares,
+            hash: 0,
         };
+        board.hash = board.full_hash();
+        board
+    }
 
-        let mut board =
-            (0..size)
-            .map(|_| (0..size)
-            .map(|_| Square::new())
-            .collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-
-        let mid_of_board = ((size - 1) / 2) as usize;
-        board[mid_of_board][mid_of_board].piece = Some(Piece::King);
-        board[mid_of_board][mid_of_board].status = Some(Status::Castle);
-
-        board[0][0].status = Some(Status::Corner);
-        board[0][(size - 1) as usize].status = Some(Status::Corner);
-        board[(size - 1) as usize][0].status = Some(Status::Corner);
-        board[(size - 1) as usize][(size - 1) as usize].status = Some(Status::Corner);
-
-        match rule {
-            Rule::Hnefatafl11 | Rule::Hnefatafl13 => {
-                // Swedes
-                board[mid_of_board - 1][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board + 1][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board - 2][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board + 2][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board - 1].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board + 1].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board - 2].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board + 2].piece = Some(Piece::Swede);
-                board[mid_of_board - 1][mid_of_board - 1].piece = Some(Piece::Swede);
-                board[mid_of_board - 1][mid_of_board + 1].piece = Some(Piece::Swede);
-                board[mid_of_board + 1][mid_of_board - 1].piece = Some(Piece::Swede);
-                board[mid_of_board + 1][mid_of_board + 1].piece = Some(Piece::Swede);
-
-                //Muscovites
-                board[mid_of_board][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board][1].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 1][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 1][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 2][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 2][0].piece = Some(Piece::Muscovite);
-
-                board[mid_of_board][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board][(size - 2) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 1][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 1][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 2][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 2][(size - 1) as usize].piece = Some(Piece::Muscovite);
-
-                board[0][mid_of_board].piece = Some(Piece::Muscovite);
-                board[1][mid_of_board].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board - 1].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board + 1].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board - 2].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board + 2].piece = Some(Piece::Muscovite);
-
-                board[(size - 1) as usize][mid_of_board].piece = Some(Piece::Muscovite);
-                board[(size - 2) as usize][mid_of_board].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board - 1].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board + 1].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board - 2].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board + 2].piece = Some(Piece::Muscovite);
-            },
-            Rule::Tablut => {
-                // Swedes
-                board[mid_of_board - 1][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board + 1][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board - 2][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board + 2][mid_of_board].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board - 1].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board + 1].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board - 2].piece = Some(Piece::Swede);
-                board[mid_of_board][mid_of_board + 2].piece = Some(Piece::Swede);
-
-                //Muscovites
-                board[mid_of_board][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board][1].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 1][0].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 1][0].piece = Some(Piece::Muscovite);
-
-                board[mid_of_board][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board][(size - 2) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board - 1][(size - 1) as usize].piece = Some(Piece::Muscovite);
-                board[mid_of_board + 1][(size - 1) as usize].piece = Some(Piece::Muscovite);
-
-                board[0][mid_of_board].piece = Some(Piece::Muscovite);
-                board[1][mid_of_board].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board - 1].piece = Some(Piece::Muscovite);
-                board[0][mid_of_board + 1].piece = Some(Piece::Muscovite);
-
-                board[(size - 1) as usize][mid_of_board].piece = Some(Piece::Muscovite);
-                board[(size - 2) as usize][mid_of_board].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board - 1].piece = Some(Piece::Muscovite);
-                board[(size - 1) as usize][mid_of_board + 1].piece = Some(Piece::Muscovite);
-            },
+    /// Set up a position on the board of `rule` from a diagram, written like
+    /// the board of a variant file with one line per row, see
+    /// [`variant`](super::variant). Blank lines and indentation are ignored.
+    ///
+    /// The special squares are those of `rule`. Marking them in the diagram
+    /// is optional, but a marker on any other square is an error.
+    ///
+    /// ```
+    /// use tafl::{Board, Piece, Rule};
+    ///
+    /// let board = Board::from_ascii(Rule::Brandubh, "
+    ///     + . . . . . +
+    ///     . . . M . . .
+    ///     . . . S . . .
+    ///     . . . K# . . .
+    ///     . . . . . . .
+    ///     . . . . . . .
+    ///     + . . . . . +
+    /// ").unwrap();
+    /// assert_eq!(board.get_piece_at((3, 2)), Some(Piece::Swede));
+    /// ```
+    pub fn from_ascii(rule: Rule, diagram: &str) -> Result<Self, VariantError> {
+        let layout = Board::new(rule);
+        let lines = diagram.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let rows = variant::parse_rows(lines, layout.size, diagram.lines().count())?;
+
+        let mut squares = layout.board;
+        for (y, (line, row)) in rows.into_iter().enumerate() {
+            for (x, square) in row.into_iter().enumerate() {
+                let status = squares[x][y].status;
+                if square.status.is_some() && square.status != status {
+                    return Err(VariantError {
+                        line,
+                        message: format!("square `{}` does not match the board of {}", variant::format_square(square), rule),
+                    });
+                }
+                squares[x][y].piece = square.piece;
+            }
         }
 
-        Self {
-            board,
-            rule,
-            size,
-        }
+        Ok(Self::from_squares(squares))
     }
 
+    /// The board as a diagram that `from_ascii` reads back, one line per row
+    /// with the special squares marked.
+    pub fn to_ascii(&self) -> String {
+        let size = self.size as usize;
+        (0..size)
+            .map(|y| {
+                let row = (0..size)
+                    .map(|x| variant::format_square(self.board[x][y]))
+                    .collect::<Vec<_>>();
+                row.join(" ") + "\n"
+            })
+            .collect()
+    }
+
+    /// The Zobrist hash of the piece placement.
+    ///
+    /// It is kept up to date by `move_piece`, `set_piece` and `remove_piece`,
+    /// so squares should not be changed through `board` directly.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
 
-    pub fn get_piece_at(&mut self, at: Coord) -> Option<Piece> {
-        return self.board[at.0][at.1].piece;
+    fn full_hash(&self) -> u64 {
+        let size = self.size as usize;
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| (x, y)))
+            .filter_map(|at| self.get_piece_at(at).map(|piece| zobrist::key(at, piece)))
+            .fold(0, |hash, key| hash ^ key)
     }
 
-    /* Same as `get_piece_at`, but will return `None` if `at` is not within the board.*/
-    pub fn get_piece_at_safe(&mut self, at: Coord) -> Option<Piece> {
+
+    /// The piece standing at `at`, if any.
+    pub fn get_piece_at(&self, at: Coord) -> Option<Piece> {
+        self.board[at.0][at.1].piece
+    }
+
+    /// Same as `get_piece_at`, but will return `None` if `at` is not within the board.
+    pub fn get_piece_at_safe(&self, at: Coord) -> Option<Piece> {
         if (at.0 < self.size as usize) && (at.1 < self.size as usize) {
-            return self.board[at.0][at.1].piece;
+            self.board[at.0][at.1].piece
+        } else {
+            None
+        }
+    }
+
+    /// The square next to `at` in `direction`, if it is on the board.
+    pub fn step(&self, at: Coord, direction: (isize, isize)) -> Option<Coord> {
+        let x = at.0.checked_add_signed(direction.0)?;
+        let y = at.1.checked_add_signed(direction.1)?;
+        if x < self.size as usize && y < self.size as usize {
+            Some((x, y))
         } else {
-            return None;
+            None
         }
     }
 
-    pub fn get_status_at(&mut self, at: Coord) -> Option<Status> {
-        return self.board[at.0][at.1].status;
+    /// Whether `at` is a special square.
+    pub fn get_status_at(&self, at: Coord) -> Option<Status> {
+        self.board[at.0][at.1].status
     }
 
+    /// Move whatever is at `from` to `to` without checking any rules.
     pub fn move_piece(&mut self, from: Coord, to: Coord) {
-        // let piece = self.board[from.0][from.1].piece;
-        let piece = self.get_piece_at(from);
-        self.board[from.0][from.1].piece = None;
-        self.board[to.0][to.1].piece = piece;
+        let piece = self.remove_piece(from);
+        self.set_piece(to, piece);
+    }
+
+    /// Put `piece` at `at`, replacing whatever stood there.
+    pub fn set_piece(&mut self, at: Coord, piece: Option<Piece>) {
+        self.remove_piece(at);
+        if let Some(p) = piece {
+            self.hash ^= zobrist::key(at, p);
+        }
+        self.board[at.0][at.1].piece = piece;
+    }
+
+    /// Take the piece at `at` off the board and return it.
+    pub fn remove_piece(&mut self, at: Coord) -> Option<Piece> {
+        let piece = self.board[at.0][at.1].piece.take();
+        if let Some(p) = piece {
+            self.hash ^= zobrist::key(at, p);
+        }
+        piece
     }
 }
 
+#[cfg(test)]
+use super::rules::Escape;
+
 #[test]
 fn test_new_board() -> std::io::Result<()> {
     let board = Board::new(Rule::Tablut);
-    assert_eq!(board.size, SIZE_TABLUT, "Board is not sized for Tablut");
+    assert_eq!(board.size, 9, "Board is not sized for Tablut");
+    Ok(())
+}
+
+#[test]
+fn test_layouts() -> std::io::Result<()> {
+    for name in Rule::variants() {
+        let rule: Rule = name.parse().unwrap();
+        let variant = rule.variant();
+        assert_eq!(variant.name, *name, "{} file has the wrong name", rule);
+        assert_eq!(variant.board.board.len(), variant.board.size as usize, "{} board is not square", rule);
+        let mid = ((variant.board.size - 1) / 2) as usize;
+        assert_eq!(variant.board.get_piece_at((mid, mid)), Some(Piece::King), "{} king is not on the throne", rule);
+        assert_eq!(variant.board.get_status_at((mid, mid)), Some(Status::Castle), "{} has no throne", rule);
+        let corner = variant.board.get_status_at((0, 0)) == Some(Status::Corner);
+        assert_eq!(corner, variant.rules.escape == Escape::Corner, "{} corners do not match its escape", rule);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_small_layouts() -> std::io::Result<()> {
+    let count = |board: &Board, piece: Piece| {
+        board.board.iter().flatten().filter(|square| square.piece == Some(piece)).count()
+    };
+
+    let board = Board::new(Rule::Brandubh);
+    assert_eq!(count(&board, Piece::Muscovite), 8, "wrong number of Muscovites in Brandubh");
+    assert_eq!(count(&board, Piece::Swede), 4, "wrong number of Swedes in Brandubh");
+    assert_eq!(board.get_piece_at((3, 2)), Some(Piece::Swede), "Brandubh Swedes do not form a cross");
+    assert_eq!(board.get_piece_at((2, 2)), None, "Brandubh Swedes do not form a cross");
+
+    let board = Board::new(Rule::ArdRi);
+    assert_eq!(count(&board, Piece::Muscovite), 16, "wrong number of Muscovites in Ard Ri");
+    assert_eq!(count(&board, Piece::Swede), 8, "wrong number of Swedes in Ard Ri");
+    assert_eq!(board.get_piece_at((2, 2)), Some(Piece::Swede), "Ard Ri Swedes do not form a block");
+    Ok(())
+}
+
+#[test]
+fn test_alea_evangelii_layout() -> std::io::Result<()> {
+    let count = |board: &Board, piece: Piece| {
+        board.board.iter().flatten().filter(|square| square.piece == Some(piece)).count()
+    };
+
+    let board = Board::new(Rule::AleaEvangelii);
+    assert_eq!(board.size, 19, "Alea Evangelii is not played on 19x19");
+    assert_eq!(count(&board, Piece::Muscovite), 48, "wrong number of Muscovites");
+    assert_eq!(count(&board, Piece::Swede), 24, "wrong number of Swedes");
+    assert_eq!(board.get_status_at((18, 18)), Some(Status::Corner), "corner is not special");
+    for x in 0..19 {
+        for y in 0..19 {
+            assert_eq!(board.get_piece_at((x, y)), board.get_piece_at((y, x)), "layout is not symmetric");
+            assert_eq!(board.get_piece_at((x, y)), board.get_piece_at((18 - x, y)), "layout is not symmetric");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tawlbwrdd_layout() -> std::io::Result<()> {
+    let board = Board::new(Rule::Tawlbwrdd);
+    let mid: usize = 5;
+    let swedes = board.board.iter().flatten().filter(|square| square.piece == Some(Piece::Swede)).count();
+    assert_eq!(swedes, 12, "wrong number of Swedes");
+    for x in 0..11usize {
+        for y in 0..11usize {
+            let in_diamond = x.abs_diff(mid) + y.abs_diff(mid) <= 2 && (x, y) != (mid, mid);
+            let swede = board.get_piece_at((x, y)) == Some(Piece::Swede);
+            assert_eq!(swede, in_diamond, "Swedes do not form a diamond at {:?}", (x, y));
+        }
+    }
+    assert_eq!(board.get_status_at((0, 0)), None, "corner is special with edge escape");
+    Ok(())
+}
+
+#[test]
+fn test_ashton_tablut_layout() -> std::io::Result<()> {
+    let board = Board::new(Rule::AshtonTablut);
+    let camps = board.board.iter().flatten().filter(|square| square.status == Some(Status::Camp));
+    assert_eq!(camps.clone().count(), 16, "wrong number of camp squares");
+    assert!(camps.clone().all(|square| square.piece == Some(Piece::Muscovite)), "camp without a Muscovite");
+    let muscovites = board.board.iter().flatten().filter(|square| square.piece == Some(Piece::Muscovite)).count();
+    assert_eq!(muscovites, 16, "Muscovite outside of the camps");
+    assert_eq!(board.get_status_at((4, 1)), Some(Status::Camp), "camps are not T-shaped");
+    Ok(())
+}
+
+#[test]
+fn test_ascii_round_trip() -> std::io::Result<()> {
+    for name in Rule::variants() {
+        let rule: Rule = name.parse().unwrap();
+        let board = Board::new(rule);
+        let copy = Board::from_ascii(rule, &board.to_ascii()).unwrap();
+        assert_eq!(copy.board, board.board, "{} does not round trip", rule);
+        assert_eq!(copy.hash(), board.hash(), "{} hash differs", rule);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_from_ascii() -> std::io::Result<()> {
+    let board = Board::from_ascii(Rule::Brandubh, "
+        . . . . . . .
+        . . . M . . .
+        . . . . . . .
+        . . S K S . .
+        . . . . . . .
+        . . . . . . .
+        . . . . . . M
+    ").unwrap();
+    assert_eq!(board.get_piece_at((3, 3)), Some(Piece::King), "king is missing");
+    assert_eq!(board.get_piece_at((6, 6)), Some(Piece::Muscovite), "Muscovite in the corner is missing");
+    assert_eq!(board.get_piece_at((3, 0)), None, "piece of the initial layout is left");
+    assert_eq!(board.get_status_at((3, 3)), Some(Status::Castle), "unmarked throne is missing");
+    assert_eq!(board.get_status_at((0, 0)), Some(Status::Corner), "unmarked corner is missing");
+    assert_eq!(board.hash(), board.full_hash(), "hash does not cover the pieces");
+    assert_eq!(board.to_ascii().lines().nth(3), Some(". . S K# S . ."), "throne is not marked");
+
+    let err = Board::from_ascii(Rule::Brandubh, "
+        . . . . . . .
+        . . . . . . .
+        . . . + . . .
+        . . . K# . . .
+        . . . . . . .
+        . . . . . . .
+        . . . . . . .
+    ").err();
+    assert_eq!(err.map(|err| err.line), Some(4), "marker on a normal square not reported on its line");
+
+    let err = Board::from_ascii(Rule::Brandubh, ". . . K# . . .").err();
+    assert_eq!(err.map(|err| err.message), Some("expected 7 rows, found 1".to_string()));
     Ok(())
 }
 
 #[test]
 fn test_get_piece_at_empty() -> std::io::Result<()> {
-    let mut board = Board::new(Rule::Tablut);
+    let board = Board::new(Rule::Tablut);
     let piece = board.get_piece_at((0, 0));
     assert_eq!(piece, None, "piece is not None");
     Ok(())
@@ -191,7 +344,7 @@ fn test_get_piece_at_empty() -> std::io::Result<()> {
 
 #[test]
 fn test_get_piece_at_non_empty() -> std::io::Result<()> {
-    let mut board = Board::new(Rule::Tablut);
+    let board = Board::new(Rule::Tablut);
     let piece = board.get_piece_at((0, 5));
     assert_eq!(piece, Some(Piece::Muscovite), "piece is not some muscovite");
     Ok(())
@@ -199,7 +352,7 @@ fn test_get_piece_at_non_empty() -> std::io::Result<()> {
 
 #[test]
 fn test_get_status_at_normal() -> std::io::Result<()> {
-    let mut board = Board::new(Rule::Tablut);
+    let board = Board::new(Rule::Tablut);
     let stat = board.get_status_at((1, 1));
     assert_eq!(stat, None, "square is not normal");
     Ok(())
@@ -207,15 +360,25 @@ fn test_get_status_at_normal() -> std::io::Result<()> {
 
 #[test]
 fn test_get_status_at_corner() -> std::io::Result<()> {
-    let mut board = Board::new(Rule::Tablut);
+    let board = Board::new(Rule::Tablut);
     let stat00 = board.get_status_at((0, 0));
     assert_eq!(stat00, Some(Status::Corner), "square (0, 0) is not corner");
     Ok(())
 }
 
+#[test]
+fn test_no_corners_with_edge_escape() -> std::io::Result<()> {
+    let board = Board::new(Rule::LinnaeusTablut);
+    let last = (board.size - 1) as usize;
+    for corner in [(0, 0), (0, last), (last, 0), (last, last)] {
+        assert_eq!(board.get_status_at(corner), None, "corner {:?} is special", corner);
+    }
+    Ok(())
+}
+
 #[test]
 fn test_get_status_at_center() -> std::io::Result<()> {
-    let mut board = Board::new(Rule::Tablut);
+    let board = Board::new(Rule::Tablut);
     let mid = ((board.size - 1) / 2) as usize;
     let stat_center = board.get_status_at((mid, mid));
     assert_eq!(stat_center, Some(Status::Castle), "center square is not castle");
@@ -233,3 +396,17 @@ fn test_move_piece() -> std::io::Result<()> {
     assert_eq!(piece1, piece2, "moving piece failed");
     Ok(())
 }
+
+#[test]
+fn test_hash_is_incremental() -> std::io::Result<()> {
+    let mut board = Board::new(Rule::Tablut);
+    let initial = board.hash();
+    board.move_piece((3, 0), (3, 2));
+    board.remove_piece((4, 2));
+    assert_ne!(board.hash(), initial, "hash did not change");
+    assert_eq!(board.hash(), board.full_hash(), "incremental hash differs from full hash");
+    board.set_piece((4, 2), Some(Piece::Swede));
+    board.move_piece((3, 2), (3, 0));
+    assert_eq!(board.hash(), initial, "hash did not return to the initial one");
+    Ok(())
+}
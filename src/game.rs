@@ -1,220 +1,2790 @@
-use structopt::clap::arg_enum;
+use std::{fmt::{self, Display}, str::FromStr};
 
-use super::piece::{Piece, Team};
-use super::board::{Board, Status};
-
-// pub const SIZE_ALEA_EVANGELII: u16 = 19;
-// pub const SIZE_ARD_RI: u16 = 7;
-// pub const SIZE_BRANDUBH: u16 = 7;
-pub const SIZE_HNEFATAFL_11: u16 = 11;
-pub const SIZE_HNEFATAFL_13: u16 = 13;
-pub const SIZE_TABLUT: u16 = 9;
-// pub const SIZE_TAWLBWRRD: u16 = 11;
+use super::piece::{Capabilities, Capture, Piece, Team};
+use super::board::{Board, Status, DIRECTIONS};
+use super::rules::{Escape, Repetition, RuleSet};
+use super::variant::Variant;
+use super::zobrist;
 
 
+/// A position on the board as `(column, row)`, with `(0, 0)` in the top
+/// left corner.
 pub type Coord = (usize, usize);
 
 
-arg_enum!{
-#[derive(Debug, Clone, Copy)]
+/// The variant of tafl being played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Rule {
     Hnefatafl11,
     Hnefatafl13,
     Tablut,
+    /// Tablut as described by Linnaeus, where the king escapes to any square
+    /// along the edge.
+    LinnaeusTablut,
+    Brandubh,
+    ArdRi,
+    AleaEvangelii,
+    /// The Welsh game, with edge escape.
+    Tawlbwrdd,
+    /// Hnefatafl on 11x11 with the Copenhagen rules.
+    Copenhagen,
+    /// Hnefatafl on 11x11 with the Fetlar rules.
+    Fetlar,
+    /// Hnefatafl on 11x11 with commanders, knights and berserk captures.
+    Berserk,
+    /// Tablut with the Ashton rules, where the Muscovites start in camps.
+    AshtonTablut,
+}
+
+/// Every built-in variant with its name, as accepted by `FromStr`, and its
+/// definition.
+const VARIANTS: &[(Rule, &str, &str)] = &[
+    (Rule::Hnefatafl11, "Hnefatafl11", include_str!("../variants/hnefatafl11.variant")),
+    (Rule::Hnefatafl13, "Hnefatafl13", include_str!("../variants/hnefatafl13.variant")),
+    (Rule::Tablut, "Tablut", include_str!("../variants/tablut.variant")),
+    (Rule::LinnaeusTablut, "LinnaeusTablut", include_str!("../variants/linnaeustablut.variant")),
+    (Rule::Brandubh, "Brandubh", include_str!("../variants/brandubh.variant")),
+    (Rule::ArdRi, "ArdRi", include_str!("../variants/ardri.variant")),
+    (Rule::AleaEvangelii, "AleaEvangelii", include_str!("../variants/aleaevangelii.variant")),
+    (Rule::Tawlbwrdd, "Tawlbwrdd", include_str!("../variants/tawlbwrdd.variant")),
+    (Rule::Copenhagen, "Copenhagen", include_str!("../variants/copenhagen.variant")),
+    (Rule::Fetlar, "Fetlar", include_str!("../variants/fetlar.variant")),
+    (Rule::Berserk, "Berserk", include_str!("../variants/berserk.variant")),
+    (Rule::AshtonTablut, "AshtonTablut", include_str!("../variants/ashtontablut.variant")),
+];
+
+/// The names from `VARIANTS`, in the same order.
+const NAMES: [&str; VARIANTS.len()] = {
+    let mut names = [""; VARIANTS.len()];
+    let mut i = 0;
+    while i < VARIANTS.len() {
+        names[i] = VARIANTS[i].1;
+        i += 1;
+    }
+    names
+};
+
+impl Rule {
+    /// Names of all variants, as accepted by `FromStr`.
+    pub fn variants() -> &'static [&'static str] {
+        &NAMES
+    }
+
+    /// The entry of this variant in `VARIANTS`.
+    fn entry(self) -> &'static (Rule, &'static str, &'static str) {
+        match VARIANTS.iter().find(|&&(rule, _, _)| rule == self) {
+            Some(entry) => entry,
+            None => panic!("variant {:?} is missing from the variant table", self),
+        }
+    }
+
+    /// The built-in definition of this variant.
+    pub fn variant(self) -> Variant {
+        match self.entry().2.parse() {
+            Ok(variant) => variant,
+            Err(err) => panic!("built-in variant {} is invalid: {}", self, err),
+        }
+    }
+
+    /// The rules this variant is played with.
+    pub fn rules(self) -> RuleSet {
+        self.variant().rules
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.entry().1)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VARIANTS
+            .iter()
+            .find(|(_, name, _)| s.eq_ignore_ascii_case(name))
+            .map(|&(rule, _, _)| rule)
+            .ok_or_else(|| format!("valid values: {}", Rule::variants().join(", ")))
+    }
 }
+
+
+/// How a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Team),
+    Draw,
+}
+
+impl GameResult {
+    /// The winning team, unless the game was drawn.
+    pub fn winner(self) -> Option<Team> {
+        match self {
+            GameResult::Win(team) => Some(team),
+            GameResult::Draw => None,
+        }
+    }
+}
+
+/// A move of a piece from one square to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Coord,
+    pub to: Coord,
+}
+
+
+/// Why a move was rejected by [`Game::move_piece`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// The game has already ended.
+    GameOver,
+    /// One of the coordinates lies outside the board.
+    OutOfBounds,
+    /// There is no piece on the starting square.
+    NoPiece,
+    /// The piece belongs to the side that is not on the move.
+    NotYourPiece,
+    /// The starting and target square are the same.
+    NoMovement,
+    /// Pieces only move along rows and columns.
+    Diagonal,
+    /// Another piece stands in the way.
+    PathBlocked,
+    /// The target is a special square the piece may not enter.
+    RestrictedSquare,
+    /// The target square is already occupied.
+    OccupiedTarget,
+    /// Only the piece that just captured may move again in this turn.
+    NotContinuing,
+    /// A piece moving again in a berserk turn has to capture, or escape if
+    /// it is the king.
+    NoCapture,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            MoveError::GameOver => "the game is over",
+            MoveError::OutOfBounds => "the move leaves the board",
+            MoveError::NoPiece => "there is no piece to move",
+            MoveError::NotYourPiece => "the piece belongs to the other side",
+            MoveError::NoMovement => "the piece has to move",
+            MoveError::Diagonal => "pieces can not move diagonally",
+            MoveError::PathBlocked => "another piece is in the way",
+            MoveError::RestrictedSquare => "the piece can not enter that square",
+            MoveError::OccupiedTarget => "the target square is occupied",
+            MoveError::NotContinuing => "only the piece that just captured may move",
+            MoveError::NoCapture => "the piece may only move again to capture",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+
+/// The effect of a successful move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveOutcome {
+    pub from: Coord,
+    pub to: Coord,
+    /// Squares from which pieces were captured by this move.
+    pub captured: Vec<Coord>,
+    /// The result, if the move ended the game.
+    pub result: Option<GameResult>,
+    /// Whether the turn passed to the other side. Under the berserk rule it
+    /// does not after a capture, as long as the piece can capture again.
+    pub ends_turn: bool,
+}
+
+
+/// A move as recorded in the history of a game, with everything needed to
+/// take it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub mv: Move,
+    pub piece: Piece,
+    /// Pieces captured by the move and the squares they were taken from.
+    pub captured: Vec<(Coord, Piece)>,
+    /// The result decided by this move, if any.
+    pub result: Option<GameResult>,
+    /// Whether the turn ended with this move. A turn of several moves
+    /// under the berserk rule is recorded as one entry per move.
+    pub ends_turn: bool,
 }
 
 
+/// A game in progress.
+#[derive(Clone)]
 pub struct Game {
-    pub rule: Rule,
+    /// The name of the variant being played.
+    pub variant: String,
+    pub rules: RuleSet,
     pub board: Board,
-    pub winner: Option<Team>,
+    pub result: Option<GameResult>,
     pub turn: usize,
+    /// The piece that captured and may move again in this turn.
+    continuing: Option<Coord>,
+    history: Vec<HistoryEntry>,
+    /// Moves taken back, with whether they ended their turn.
+    undone: Vec<(Move, bool)>,
+    /// Hashes of all positions so far, starting with the initial one.
+    positions: Vec<u64>,
 }
 
 impl Game {
+    /// Start a new game of `rule`.
     pub fn new(rule: Rule) -> Self {
-        Self {
-            rule,
-            board: Board::new(rule),
-            winner: None::<Team>,
+        Self::from_variant(rule.variant())
+    }
+
+    /// Start a new game with the initial layout of `rule`, but played with
+    /// `rules` instead of the variant's own.
+    pub fn with_rules(rule: Rule, rules: RuleSet) -> Self {
+        Self::from_variant(Variant { rules, ..rule.variant() })
+    }
+
+    /// Start a new game of `variant`, for example one read from a file.
+    pub fn from_variant(variant: Variant) -> Self {
+        let mut game = Self {
+            variant: variant.name,
+            rules: variant.rules,
+            board: variant.board,
+            result: None::<GameResult>,
             turn: 0,
+            continuing: None,
+            history: Vec::new(),
+            undone: Vec::new(),
+            positions: Vec::new(),
+        };
+        game.positions.push(game.hash());
+        game
+    }
+
+    /// The winning team, if the game has been won.
+    pub fn winner(&self) -> Option<Team> {
+        self.result.and_then(GameResult::winner)
+    }
+
+    /// The team whose turn it is.
+    pub fn side_to_move(&self) -> Team {
+        if self.turn.is_multiple_of(2) { self.rules.first_move } else { self.rules.first_move.opponent() }
+    }
+
+    /// What `piece` is allowed to do in this game: its own capabilities,
+    /// with the royal piece unarmed unless the rules arm the king.
+    pub fn capabilities(&self, piece: Piece) -> Capabilities {
+        let capabilities = piece.capabilities();
+        Capabilities {
+            armed: capabilities.armed && (self.rules.king_armed || ! capabilities.royal),
+            ..capabilities
         }
     }
 
-    pub fn move_piece(&mut self, from: Coord, to: Coord) {
-        let piece_at = self.board.get_piece_at(from);
-        match piece_at {
-            Some(piece) => {
-                let target_at = self.board.get_piece_at(to);
-                match target_at {
-                    Some(_) => {},
-                    None => {
-                        let invalid =
-                            (from.0 != to.0) && (from.1 != to.1)
-                            || self.check_jump(from, to)
-                            || self.check_status(from, to);
-
-                        if ! invalid {
-                            self.board.move_piece(from, to);
-                            self.check_kill(piece, to);
-                            self.check_king_escape(piece, to);
-                        }
-                    },
-                }
-                self.turn += 1;
-            },
-            None => {},
+    /// Move the piece at `from` to `to`, then resolve captures and a possible
+    /// escape of the king.
+    ///
+    /// The turn only passes to the other side if the move was legal.
+    pub fn move_piece(&mut self, from: Coord, to: Coord) -> Result<MoveOutcome, MoveError> {
+        let outcome = self.play(from, to)?;
+        self.undone.clear();
+        Ok(outcome)
+    }
+
+    /// The piece that captured and may move again before the turn passes,
+    /// under the berserk rule. Its next move has to capture as well.
+    pub fn continuing(&self) -> Option<Coord> {
+        self.continuing
+    }
+
+    /// End a turn that could go on because a piece captured under the
+    /// berserk rule.
+    ///
+    /// Returns whether there was such a turn to end.
+    pub fn end_turn(&mut self) -> bool {
+        let ended = self.finish_turn();
+        if ended {
+            self.undone.clear();
         }
+        ended
     }
 
-    fn check_status(&mut self, from: Coord, to: Coord) -> bool {
-        let piece = self.board.get_piece_at(from);
-        let status = self.board.get_status_at(to);
+    fn finish_turn(&mut self) -> bool {
+        if self.continuing.take().is_none() {
+            return false;
+        }
+        let team = self.side_to_move();
+        self.turn += 1;
+        self.positions.pop();
+        self.check_no_moves(team);
+        self.check_repetition(team);
+        if let Some(entry) = self.history.last_mut() {
+            entry.ends_turn = true;
+            entry.result = self.result;
+        }
+        true
+    }
 
-        match status {
-            Some(_) => {
-                match piece {
-                    Some(Piece::King) => { return false },
-                    _ => { return true },
-                }
-            },
-            None => { return false },
+    /// The moves played so far, oldest first.
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// Take back the last move, restoring any pieces it captured. Moves of
+    /// a berserk turn are taken back one at a time.
+    ///
+    /// Returns the move taken back, or `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+
+        self.board.move_piece(entry.mv.to, entry.mv.from);
+        for (at, piece) in entry.captured {
+            self.board.set_piece(at, Some(piece));
+        }
+        if entry.result.is_some() {
+            self.result = None;
+        }
+        if entry.ends_turn {
+            self.turn -= 1;
         }
+        self.positions.pop();
+        self.continuing = self.history
+            .last()
+            .filter(|last| ! last.ends_turn)
+            .map(|last| last.mv.to);
+
+        self.undone.push((entry.mv, entry.ends_turn));
+        Some(entry.mv)
     }
 
-    fn check_jump(&mut self, from: Coord, to: Coord) -> bool {
-        if from.0 < to.0 {
-            for i in (from.0 + 1)..(to.0) {
-                if self.check_piece((i, from.1)) { return true ; }
-            }
-        } else if from.0 > to.0 {
-            for i in (to.0)..(from.0) {
-                if self.check_piece((i, from.1)) { return true ; }
-            }
-        } else if from.1 < to.1 {
-            for i in (from.1 + 1)..(to.1) {
-                if self.check_piece((from.0, i)) { return true ; }
+    /// Play the last move taken back by `undo` again.
+    ///
+    /// Returns `None` if there is nothing to redo. Any new move made with
+    /// `move_piece` discards the moves that could be redone.
+    pub fn redo(&mut self) -> Option<MoveOutcome> {
+        let (mv, ends_turn) = self.undone.pop()?;
+        let mut outcome = self.play(mv.from, mv.to).ok()?;
+        if ends_turn && self.finish_turn() {
+            outcome.ends_turn = true;
+            outcome.result = self.result;
+        }
+        Some(outcome)
+    }
+
+    fn play(&mut self, from: Coord, to: Coord) -> Result<MoveOutcome, MoveError> {
+        let piece = self.check_move(from, to)?;
+
+        self.board.move_piece(from, to);
+        let captured = self.check_kill(piece, to);
+        self.check_king_escape(piece, to);
+        self.check_encirclement(piece);
+        self.check_exit_fort(piece);
+
+        // Under the berserk rule a piece that captured may move again, as
+        // long as it has another capture to make.
+        self.continuing = None;
+        if self.rules.berserk && ! captured.is_empty() && self.result.is_none() {
+            self.continuing = Some(to);
+            if self.legal_moves_from(to).is_empty() {
+                self.continuing = None;
             }
-        } else if from.1 > to.1 {
-            for i in (to.1)..(from.1) {
-                if self.check_piece((from.0, i)) { return true ; }
+        }
+        let ends_turn = self.continuing.is_none();
+        if ends_turn {
+            self.turn += 1;
+            self.check_no_moves(piece.get_team());
+        }
+        self.check_repetition(piece.get_team());
+
+        let outcome = MoveOutcome {
+            from,
+            to,
+            captured: captured.iter().map(|&(at, _)| at).collect(),
+            result: self.result,
+            ends_turn,
+        };
+
+        self.history.push(HistoryEntry {
+            mv: Move { from, to },
+            piece,
+            captured,
+            result: self.result,
+            ends_turn,
+        });
+
+        Ok(outcome)
+    }
+
+    /// The Zobrist hash of the position, covering the piece placement and
+    /// the side to move.
+    pub fn hash(&self) -> u64 {
+        match self.side_to_move() {
+            Team::Muscovites => self.board.hash(),
+            Team::Swedes => self.board.hash() ^ zobrist::SWEDES_TO_MOVE,
+        }
+    }
+
+    /// All legal moves of the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let size = self.board.size as usize;
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| (x, y)))
+            .flat_map(|from| self.legal_moves_from(from))
+            .collect()
+    }
+
+    /// All legal moves of the piece at `from`.
+    ///
+    /// The list is empty if there is no piece at `from` or it belongs to the
+    /// side that is not on the move.
+    pub fn legal_moves_from(&self, from: Coord) -> Vec<Move> {
+        let size = self.board.size as isize;
+        let mut moves = Vec::new();
+
+        if self.board.get_piece_at_safe(from).is_none() {
+            return moves;
+        }
+
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (mut x, mut y) = (from.0 as isize + dx, from.1 as isize + dy);
+            while x >= 0 && y >= 0 && x < size && y < size {
+                let to = (x as usize, y as usize);
+                match self.check_move(from, to) {
+                    Ok(_) => moves.push(Move { from, to }),
+                    Err(MoveError::RestrictedSquare | MoveError::NoCapture) => {},
+                    Err(_) => break,
+                }
+                x += dx;
+                y += dy;
             }
         }
-        return false;
+
+        moves
     }
 
-    fn check_piece(&mut self, at: Coord) -> bool {
-        let s = self.board.get_piece_at(at);
-        match s {
-            Some(_) => return true,
+    /// Check whether the side to move may move the piece at `from` to `to`
+    /// and return that piece.
+    fn check_move(&self, from: Coord, to: Coord) -> Result<Piece, MoveError> {
+        if self.result.is_some() {
+            return Err(MoveError::GameOver);
+        }
+
+        let size = self.board.size as usize;
+        if from.0 >= size || from.1 >= size || to.0 >= size || to.1 >= size {
+            return Err(MoveError::OutOfBounds);
+        }
+
+        let piece = self.board.get_piece_at(from).ok_or(MoveError::NoPiece)?;
+        if piece.get_team() != self.side_to_move() {
+            return Err(MoveError::NotYourPiece);
+        }
+
+        if self.continuing.is_some_and(|at| at != from) {
+            return Err(MoveError::NotContinuing);
+        }
+
+        if from == to {
+            return Err(MoveError::NoMovement);
+        }
+        if (from.0 != to.0) && (from.1 != to.1) {
+            return Err(MoveError::Diagonal);
+        }
+        if self.check_piece(to) {
+            return Err(MoveError::OccupiedTarget);
+        }
+        if self.check_jump(from, to) {
+            return Err(MoveError::PathBlocked);
+        }
+        if self.check_status(from, to) {
+            return Err(MoveError::RestrictedSquare);
+        }
+        if self.continuing.is_some() && ! self.check_berserk(piece, from, to) {
+            return Err(MoveError::NoCapture);
+        }
+
+        Ok(piece)
+    }
+
+    /// Whether moving `piece` from `from` to `to` captures or lets the king
+    /// escape, as every further move of a berserk turn has to.
+    fn check_berserk(&self, piece: Piece, from: Coord, to: Coord) -> bool {
+        let mut game = self.clone();
+        game.board.move_piece(from, to);
+        let captured = game.check_kill(piece, to);
+        game.check_king_escape(piece, to);
+        ! captured.is_empty() || game.result.is_some()
+    }
+
+    fn check_status(&self, from: Coord, to: Coord) -> bool {
+        let capabilities = match self.board.get_piece_at(from) {
+            Some(piece) => self.capabilities(piece),
             None => return false,
+        };
+
+        match self.board.get_status_at(to) {
+            Some(Status::Castle) => ! capabilities.enters_throne,
+            Some(Status::Corner) => ! capabilities.enters_corners,
+            Some(Status::Camp) => ! self.within_camp(from, to),
+            None => false,
         }
     }
 
-    fn check_kill(&mut self, piece: Piece, to: Coord) {
-        if to.0 > 1 {
-            let to_left = (to.0 - 1, to.1);
-            let over_left = (to.0 - 2, to.1);
+    fn check_jump(&self, from: Coord, to: Coord) -> bool {
+        let enters_throne = self.board
+            .get_piece_at(from)
+            .is_some_and(|piece| self.capabilities(piece).enters_throne);
+
+        between(from, to).into_iter().any(|at| {
+            self.check_piece(at)
+                || (! enters_throne
+                    && ! self.rules.throne_passable
+                    && self.board.get_status_at(at) == Some(Status::Castle))
+                || (self.board.get_status_at(at) == Some(Status::Camp) && ! self.within_camp(from, at))
+        })
+    }
+
+    /// Whether all squares from `from` to `to` are camp squares, so that a
+    /// piece moving along them never enters a camp from outside.
+    fn within_camp(&self, from: Coord, to: Coord) -> bool {
+        std::iter::once(from)
+            .chain(between(from, to))
+            .chain(std::iter::once(to))
+            .all(|at| self.board.get_status_at(at) == Some(Status::Camp))
+    }
+
+    fn check_piece(&self, at: Coord) -> bool {
+        self.board.get_piece_at(at).is_some()
+    }
 
-            if self.check_kill_rule(piece, to_left, over_left) {
-                self.board.board[to.0 - 1][to.1].piece = None;
+    fn check_kill(&mut self, piece: Piece, to: Coord) -> Vec<(Coord, Piece)> {
+        let mut captured = Vec::new();
+
+        for direction in DIRECTIONS {
+            let next = match self.board.step(to, direction) {
+                Some(next) => next,
+                None => continue,
+            };
+            let over = self.board.step(next, direction);
+
+            if self.check_kill_rule(piece, next, over) {
+                if let Some(killed) = self.board.remove_piece(next) {
+                    captured.push((next, killed));
+                }
             }
         }
 
-        if to.0 < ((self.board.size - 2) as usize) {
-            let to_right = (to.0 + 1, to.1);
-            let over_right = (to.0 + 2, to.1);
+        if self.rules.shieldwall {
+            captured.extend(self.check_shieldwall(piece, to));
+        }
+
+        captured
+    }
+
+    /// Capture rows of two or more enemies along the edge that the piece
+    /// arriving at `to` brackets together with another piece of its team or
+    /// a corner, as long as every piece in the row has an enemy in front of
+    /// it. The king may be part of a row but is never captured this way.
+    fn check_shieldwall(&mut self, piece: Piece, to: Coord) -> Vec<(Coord, Piece)> {
+        let team = piece.get_team();
+        let size = self.board.size as isize;
+        let last = size - 1;
+        let (x, y) = (to.0 as isize, to.1 as isize);
+
+        // the edges `to` lies on, as the direction pointing into the board
+        let mut inwards = Vec::new();
+        if x == 0 { inwards.push((1, 0)); }
+        if x == last { inwards.push((-1, 0)); }
+        if y == 0 { inwards.push((0, 1)); }
+        if y == last { inwards.push((0, -1)); }
+
+        let inside = |(x, y): (isize, isize)| x >= 0 && y >= 0 && x < size && y < size;
+        let at = |(x, y): (isize, isize)| (x as usize, y as usize);
 
-            if self.check_kill_rule(piece, to_right, over_right) {
-                self.board.board[to.0 + 1][to.1].piece = None;
+        let mut captured = Vec::new();
+        for (ix, iy) in inwards {
+            for (dx, dy) in [(iy, ix), (-iy, -ix)] {
+                let mut row = Vec::new();
+                let mut next = (x + dx, y + dy);
+                while inside(next) {
+                    match self.board.get_piece_at(at(next)) {
+                        Some(p) if p.get_team() != team => row.push(at(next)),
+                        _ => break,
+                    }
+                    next = (next.0 + dx, next.1 + dy);
+                }
+
+                if row.len() < 2 || ! inside(next) {
+                    continue;
+                }
+
+                let bracketed = match self.board.get_piece_at(at(next)) {
+                    Some(p) => p.get_team() == team,
+                    None => self.board.get_status_at(at(next)) == Some(Status::Corner),
+                };
+                let blocked = row.iter().all(|&(rx, ry)| {
+                    let front = (rx as isize + ix, ry as isize + iy);
+                    self.board.get_piece_at(at(front)).map(Piece::get_team) == Some(team)
+                });
+
+                if bracketed && blocked {
+                    for square in row {
+                        let royal = self.board
+                            .get_piece_at(square)
+                            .is_some_and(|piece| self.capabilities(piece).royal);
+                        if ! royal {
+                            if let Some(killed) = self.board.remove_piece(square) {
+                                captured.push((square, killed));
+                            }
+                        }
+                    }
+                }
             }
         }
 
-        if to.1 > 1 {
-            let to_up = (to.0, to.1 - 1);
-            let over_up = (to.0, to.1 - 2);
+        captured
+    }
 
-            if self.check_kill_rule(piece, to_up, over_up) {
-                self.board.board[to.0][to.1 - 1].piece = None;
-            }
+    /// Whether `piece`, having just moved next to `next_place`, captures the
+    /// piece there. `over_next_place` is the square beyond, if it is on the
+    /// board.
+    fn check_kill_rule(&mut self, piece: Piece, next_place: Coord, over_next_place: Option<Coord>) -> bool {
+        let team = piece.get_team();
+        let next = match self.board.get_piece_at(next_place) {
+            Some(next) if next.get_team() != team => next,
+            _ => return false,
+        };
+        if ! self.capabilities(piece).armed {
+            return false;
+        }
+
+        let victim = self.capabilities(next);
+        let kill = match victim.capture {
+            Capture::Custodial => over_next_place.is_some_and(|over| self.hostile_to(over, next_place, next.get_team())),
+            Capture::Enclosed(sides) => self.enclosed(next_place, next.get_team(), sides),
+            Capture::Royal => self.check_kill_king(next_place, over_next_place),
+        };
+
+        if kill && victim.royal {
+            self.result = Some(GameResult::Win(Team::Muscovites));
         }
 
-        if to.1 < ((self.board.size - 2) as usize) {
-            let to_down = (to.0, to.1 + 1);
-            let over_down = (to.0, to.1 + 2);
+        kill
+    }
+
+    fn check_kill_king(&self, at: Coord, over: Option<Coord>) -> bool {
+        let neighbours = DIRECTIONS
+            .iter()
+            .filter_map(|&direction| self.board.step(at, direction))
+            .collect::<Vec<_>>();
+
+        let near_throne = std::iter::once(at)
+            .chain(neighbours.iter().copied())
+            .any(|square| self.board.get_status_at(square) == Some(Status::Castle));
+        let sides = if self.rules.king_strong_on_throne && near_throne {
+            4
+        } else {
+            self.rules.king_capture_sides
+        };
 
-            if self.check_kill_rule(piece, to_down, over_down) {
-                self.board.board[to.0][to.1 + 1].piece = None;
+        if sides <= 2 {
+            over.is_some_and(|over| self.hostile_to(over, at, Team::Swedes))
+        } else {
+            self.enclosed(at, Team::Swedes, sides)
+        }
+    }
+
+    /// Whether the piece of `team` at `at` has at least `sides` hostile
+    /// neighbours. The edge of the board does not count.
+    fn enclosed(&self, at: Coord, team: Team, sides: u8) -> bool {
+        let hostile = DIRECTIONS
+            .iter()
+            .filter_map(|&direction| self.board.step(at, direction))
+            .filter(|&square| self.hostile_to(square, at, team))
+            .count();
+        hostile >= sides as usize
+    }
+
+    /// Whether the square `at` helps capturing the piece of `team` at
+    /// `victim`, either because an enemy stands there or because it is a
+    /// hostile square. Camps are hostile to pieces outside of them.
+    fn hostile_to(&self, at: Coord, victim: Coord, team: Team) -> bool {
+        let piece = self.board.get_piece_at(at);
+        let by_piece = piece.is_some_and(|p| p.get_team() != team && self.capabilities(p).armed);
+        let by_square = match self.board.get_status_at(at) {
+            Some(Status::Corner) => piece.is_none(),
+            Some(Status::Castle) => self.rules.throne_hostile_to(team, piece.is_some()),
+            Some(Status::Camp) => self.board.get_status_at(victim) != Some(Status::Camp),
+            None => false,
+        };
+        by_piece || by_square
+    }
+
+    fn check_king_escape(&mut self, piece: Piece, to: Coord) {
+        let last = (self.board.size - 1) as usize;
+        let escaped = match self.rules.escape {
+            Escape::Corner => self.board.get_status_at(to) == Some(Status::Corner),
+            Escape::Edge => to.0 == 0 || to.1 == 0 || to.0 == last || to.1 == last,
+        };
+        if self.capabilities(piece).royal && escaped {
+            self.result = Some(GameResult::Win(Team::Swedes));
+        }
+    }
+
+    fn check_encirclement(&mut self, piece: Piece) {
+        if piece.get_team() == Team::Muscovites
+            && self.rules.encirclement
+            && self.result.is_none()
+            && self.is_encircled() {
+            self.result = Some(GameResult::Win(Team::Muscovites));
+        }
+    }
+
+    /// Whether the Muscovites enclose all Swedes, so that none of them can
+    /// reach the edge of the board without passing a Muscovite.
+    pub fn is_encircled(&self) -> bool {
+        let size = self.board.size as usize;
+        let mut seen = vec![vec![false; size]; size];
+        let mut todo = (0..size)
+            .flat_map(|i| [(i, 0), (i, size - 1), (0, i), (size - 1, i)])
+            .collect::<Vec<Coord>>();
+
+        while let Some(at) = todo.pop() {
+            if seen[at.0][at.1] {
+                continue;
             }
+            seen[at.0][at.1] = true;
+
+            match self.board.get_piece_at(at) {
+                Some(piece) if piece.get_team() == Team::Muscovites => continue,
+                Some(_) => return false,
+                None => {},
+            }
+
+            if at.0 > 0 { todo.push((at.0 - 1, at.1)); }
+            if at.1 > 0 { todo.push((at.0, at.1 - 1)); }
+            if at.0 < size - 1 { todo.push((at.0 + 1, at.1)); }
+            if at.1 < size - 1 { todo.push((at.0, at.1 + 1)); }
         }
+
+        true
     }
 
-    fn check_kill_rule(&mut self, piece: Piece, next_place: Coord, over_next_place: Coord) -> bool {
-        let team = piece.get_team();
-        let next = self.board.get_piece_at(next_place);
-        let next_stat = self.board.get_status_at(next_place);
-        let over_next = self.board.get_piece_at_safe(over_next_place);
-        let over_next_stat = self.board.get_status_at(over_next_place);
-        let mut kill: bool = false;
-
-        let dir = next_place.0 == over_next_place.0;
-        let right_place = if dir { (next_place.0, next_place.1 + 1) }
-                          else { (next_place.0 + 1, next_place.1) };
-        let left_place = if dir { (next_place.0, next_place.1 - 1) }
-                         else { (next_place.0 - 1, next_place.1) };
-        let right = self.board.get_piece_at_safe(right_place);
-        let left = self.board.get_piece_at_safe(left_place);
-
-        if next_stat != Some(Status::Castle) {
-            match (next, over_next) {
-                (Some(n), Some(on)) => {
-                    // regular kill
-                    kill = (n.get_team() != team)
-                        && (on.get_team() == team);
-                },
-                (Some(n), None) => {
-                    // kill against corner or kill of non-king against castle
-                    kill = (n.get_team() != team)
-                        && ((over_next_stat == Some(Status::Corner))
-                            || (over_next_stat == Some(Status::Castle) && n != Piece::King));
-                }
-                _ => {},
+    fn check_exit_fort(&mut self, piece: Piece) {
+        if piece.get_team() == Team::Swedes
+            && self.rules.edge_forts
+            && self.result.is_none()
+            && self.is_exit_fort() {
+            self.result = Some(GameResult::Win(Team::Swedes));
+        }
+    }
+
+    /// Whether the king stands in an exit fort: it touches the edge of the
+    /// board, can still move, and is enclosed by Swedes none of which the
+    /// Muscovites can ever capture.
+    pub fn is_exit_fort(&self) -> bool {
+        let size = self.board.size as usize;
+        let squares = (0..size).flat_map(|x| (0..size).map(move |y| (x, y)));
+        let royal = |at: Coord| self.board.get_piece_at(at).is_some_and(|piece| self.capabilities(piece).royal);
+        let king = match squares.clone().find(|&at| royal(at)) {
+            Some(king) => king,
+            None => return false,
+        };
+
+        if king.0 != 0 && king.1 != 0 && king.0 != size - 1 && king.1 != size - 1 {
+            return false;
+        }
+
+        let neighbours = |at: Coord| {
+            [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .map(move |(dx, dy)| (at.0 as isize + dx, at.1 as isize + dy))
+                .filter(move |&(x, y)| x >= 0 && y >= 0 && x < size as isize && y < size as isize)
+                .map(|(x, y)| (x as usize, y as usize))
+        };
+
+        // The inside of the fort: the king and all empty squares it can reach.
+        let mut inside = vec![vec![false; size]; size];
+        let mut todo = vec![king];
+        let mut walls = Vec::new();
+        while let Some(at) = todo.pop() {
+            if inside[at.0][at.1] {
+                continue;
             }
-            match (next, over_next, left, right) {
-                (Some(n), None, Some(l), Some(r)) => {
-                    // kill of king against castle
-                    kill = (n == Piece::King)
-                        && (over_next_stat == Some(Status::Castle))
-                        && (l.get_team() == team)
-                        && (r.get_team() == team)
-                },
-                _ => {},
+            inside[at.0][at.1] = true;
+
+            for n in neighbours(at) {
+                match self.board.get_piece_at(n) {
+                    None => todo.push(n),
+                    Some(piece) if piece.get_team() == Team::Muscovites => return false,
+                    Some(_) if royal(n) => {},
+                    Some(_) => walls.push(n),
+                }
             }
-        } else {
-            match (next, over_next, left, right) {
-                (Some(n), Some(on), Some(l), Some(r)) => {
-                    // kill in castle
-                    kill = (n.get_team() != team)
-                        && (on.get_team() == team)
-                        && (l.get_team() == team)
-                        && (r.get_team() == team)
-                },
-                _ => {},
+        }
+
+        if ! neighbours(king).any(|n| inside[n.0][n.1] && n != king) {
+            return false;
+        }
+
+        // A Swede is safe on an axis if at least one side is off the board,
+        // inside the fort or another safe Swede. Start with all Swedes and
+        // drop the unsafe ones until only safe ones remain.
+        let mut safe = squares
+            .filter(|&at| self.board.get_piece_at(at).map(Piece::get_team) == Some(Team::Swedes) && ! royal(at))
+            .collect::<Vec<_>>();
+        loop {
+            let covered = |x: isize, y: isize| {
+                x < 0 || y < 0 || x >= size as isize || y >= size as isize
+                    || inside[x as usize][y as usize]
+                    || safe.contains(&(x as usize, y as usize))
+            };
+            let before = safe.len();
+            let still_safe = safe
+                .iter()
+                .copied()
+                .filter(|&(x, y)| {
+                    let (x, y) = (x as isize, y as isize);
+                    (covered(x - 1, y) || covered(x + 1, y)) && (covered(x, y - 1) || covered(x, y + 1))
+                })
+                .collect::<Vec<_>>();
+            safe = still_safe;
+            if safe.len() == before {
+                break;
             }
         }
 
+        walls.iter().all(|wall| safe.contains(wall))
+    }
+
+    /// End the game in favour of `team`, which just moved, if the other side
+    /// has no legal moves left.
+    fn check_no_moves(&mut self, team: Team) {
+        if self.result.is_none() && self.legal_moves().is_empty() {
+            self.result = Some(GameResult::Win(team));
+        }
+    }
+
+    /// Record the position reached by a move of `team` and end the game if
+    /// it occurred for the third time.
+    fn check_repetition(&mut self, team: Team) {
+        let hash = self.hash();
+        self.positions.push(hash);
 
-        let kill_king = (next == Some(Piece::King)) && kill;
-        if kill_king { self.winner = Some(Team::Muscovites); }
+        let count = self.positions.iter().filter(|&&h| h == hash).count();
+        if count < 3 || self.result.is_some() {
+            return;
+        }
 
-        return kill;
+        self.result = match self.rules.repetition {
+            Repetition::Draw => Some(GameResult::Draw),
+            Repetition::PerpetualLoss => Some(GameResult::Win(team.opponent())),
+        };
     }
+}
 
-    fn check_king_escape(&mut self, piece: Piece, to: Coord) {
-        let s = self.board.get_status_at(to);
-        if (piece == Piece::King) && s == Some(Status::Corner) {
-            self.winner = Some(Team::Swedes);
+/// The squares strictly between `from` and `to`, which lie on one line.
+fn between(from: Coord, to: Coord) -> Vec<Coord> {
+    if from.0 == to.0 {
+        (from.1.min(to.1) + 1..from.1.max(to.1)).map(|y| (from.0, y)).collect()
+    } else {
+        (from.0.min(to.0) + 1..from.0.max(to.0)).map(|x| (x, from.1)).collect()
+    }
+}
+
+#[cfg(test)]
+use super::variant::setup;
+
+#[test]
+fn test_move_piece_advances_turn() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    let outcome = game.move_piece((3, 0), (3, 2));
+    assert!(outcome.is_ok(), "legal move was rejected");
+    assert_eq!(game.turn, 1, "turn did not advance");
+    assert_eq!(game.side_to_move(), Team::Swedes, "Swedes are not on the move");
+    Ok(())
+}
+
+#[test]
+fn test_move_piece_rejects_illegal_moves() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    assert_eq!(game.move_piece((1, 1), (1, 2)), Err(MoveError::NoPiece));
+    assert_eq!(game.move_piece((4, 2), (2, 2)), Err(MoveError::NotYourPiece));
+    assert_eq!(game.move_piece((3, 0), (3, 0)), Err(MoveError::NoMovement));
+    assert_eq!(game.move_piece((3, 0), (2, 1)), Err(MoveError::Diagonal));
+    assert_eq!(game.move_piece((4, 1), (4, 2)), Err(MoveError::OccupiedTarget));
+    assert_eq!(game.move_piece((3, 0), (3, 5)), Err(MoveError::PathBlocked));
+    assert_eq!(game.move_piece((3, 0), (0, 0)), Err(MoveError::RestrictedSquare));
+    assert_eq!(game.move_piece((3, 0), (3, 9)), Err(MoveError::OutOfBounds));
+    assert_eq!(game.turn, 0, "illegal move advanced the turn");
+    assert_eq!(game.board.get_piece_at((3, 0)), Some(Piece::Muscovite), "illegal move moved a piece");
+    Ok(())
+}
+
+#[test]
+fn test_move_piece_reports_captures() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((2, 4), (2, 6)).unwrap();
+    let outcome = game.move_piece((5, 0), (5, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(4, 2)], "capture was not reported");
+    assert_eq!(outcome.result, None, "capture ended the game");
+    assert_eq!(game.board.get_piece_at((4, 2)), None, "captured piece is still on the board");
+    Ok(())
+}
+
+#[test]
+fn test_move_piece_after_game_over() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    game.result = Some(GameResult::Win(Team::Swedes));
+    assert_eq!(game.move_piece((3, 0), (3, 2)), Err(MoveError::GameOver));
+    Ok(())
+}
+
+#[test]
+fn test_legal_moves_from() -> std::io::Result<()> {
+    let game = Game::new(Rule::Tablut);
+    let moves = game.legal_moves_from((4, 1));
+    assert_eq!(moves.len(), 8, "wrong number of moves for (4, 1)");
+    assert!(moves.contains(&Move { from: (4, 1), to: (0, 1) }), "move to the edge is missing");
+    assert!(game.legal_moves_from((4, 2)).is_empty(), "Swedes can move on the Muscovites' turn");
+    assert!(game.legal_moves_from((1, 1)).is_empty(), "empty square has moves");
+    Ok(())
+}
+
+#[test]
+fn test_legal_moves_match_move_piece() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Hnefatafl11);
+    game.move_piece((5, 1), (2, 1)).unwrap();
+    game.move_piece((5, 3), (2, 3)).unwrap();
+
+    let size = game.board.size as usize;
+    let moves = game.legal_moves();
+    for from in (0..size).flat_map(|x| (0..size).map(move |y| (x, y))) {
+        for to in (0..size).flat_map(|x| (0..size).map(move |y| (x, y))) {
+            let legal = game.clone().move_piece(from, to).is_ok();
+            assert_eq!(legal, moves.contains(&Move { from, to }), "{:?} -> {:?}", from, to);
         }
     }
+    Ok(())
+}
+
+#[test]
+fn test_undo_restores_captures() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((2, 4), (2, 6)).unwrap();
+    game.move_piece((5, 0), (5, 2)).unwrap();
+
+    let undone = game.undo();
+    assert_eq!(undone, Some(Move { from: (5, 0), to: (5, 2) }), "wrong move undone");
+    assert_eq!(game.turn, 2, "turn was not taken back");
+    assert_eq!(game.board.get_piece_at((5, 0)), Some(Piece::Muscovite), "piece was not moved back");
+    assert_eq!(game.board.get_piece_at((4, 2)), Some(Piece::Swede), "captured piece was not restored");
+    assert_eq!(game.history().len(), 2, "history was not shortened");
+    Ok(())
+}
+
+#[test]
+fn test_redo_replays_move() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((2, 4), (2, 6)).unwrap();
+    game.move_piece((5, 0), (5, 2)).unwrap();
+    game.undo();
+    game.undo();
+
+    let outcome = game.redo().unwrap();
+    assert_eq!((outcome.from, outcome.to), ((2, 4), (2, 6)), "wrong move redone");
+    let outcome = game.redo().unwrap();
+    assert_eq!(outcome.captured, vec![(4, 2)], "redone capture was not reported");
+    assert_eq!(game.redo(), None, "redo past the end of the history");
+    Ok(())
+}
+
+#[test]
+fn test_move_discards_redo() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    assert_eq!(game.undo(), None, "undo of an empty history");
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.undo();
+    game.move_piece((5, 0), (5, 2)).unwrap();
+    assert_eq!(game.redo(), None, "new move did not discard the undone one");
+    Ok(())
+}
+
+#[test]
+fn test_hash_covers_side_to_move() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    let initial = game.hash();
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((2, 4), (2, 6)).unwrap();
+    game.move_piece((3, 2), (3, 0)).unwrap();
+    game.move_piece((2, 6), (2, 4)).unwrap();
+    assert_eq!(game.hash(), initial, "same position hashes differently");
+
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    let hash = game.hash();
+    game.board.move_piece((3, 2), (3, 0));
+    assert_ne!(game.hash(), initial, "hash ignores the side to move");
+    game.board.move_piece((3, 0), (3, 2));
+    assert_eq!(game.hash(), hash, "hash is not restored");
+    Ok(())
+}
+
+#[test]
+fn test_hash_after_capture_and_undo() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((2, 4), (2, 6)).unwrap();
+    let before = game.hash();
+    game.move_piece((5, 0), (5, 2)).unwrap();
+    assert_eq!(game.board.hash(), Game::new(Rule::Tablut).board.hash()
+        ^ zobrist::key((3, 0), Piece::Muscovite) ^ zobrist::key((3, 2), Piece::Muscovite)
+        ^ zobrist::key((5, 0), Piece::Muscovite) ^ zobrist::key((5, 2), Piece::Muscovite)
+        ^ zobrist::key((2, 4), Piece::Swede) ^ zobrist::key((2, 6), Piece::Swede)
+        ^ zobrist::key((4, 2), Piece::Swede), "capture is not hashed");
+    game.undo();
+    assert_eq!(game.hash(), before, "undo does not restore the hash");
+    Ok(())
+}
+
+#[test]
+fn test_threefold_repetition_draw() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . M . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . S . K# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    "));
+    for _ in 0..2 {
+        game.move_piece((3, 0), (3, 2)).unwrap();
+        game.move_piece((2, 4), (2, 6)).unwrap();
+        game.move_piece((3, 2), (3, 0)).unwrap();
+        assert_eq!(game.result, None, "game ended before the third repetition");
+        game.move_piece((2, 6), (2, 4)).unwrap();
+    }
+    assert_eq!(game.result, Some(GameResult::Draw), "third repetition is not a draw");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . M . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . S . K# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the repetitions");
+    assert_eq!(game.move_piece((3, 0), (3, 2)), Err(MoveError::GameOver));
+
+    game.undo();
+    assert_eq!(game.result, None, "undo did not reopen the game");
+    Ok(())
+}
+
+#[test]
+fn test_perpetual_repetition_loses() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . M . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . S . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    for _ in 0..2 {
+        game.move_piece((3, 0), (3, 2)).unwrap();
+        game.move_piece((3, 5), (3, 7)).unwrap();
+        game.move_piece((3, 2), (3, 0)).unwrap();
+        game.move_piece((3, 7), (3, 5)).unwrap();
+    }
+    assert_eq!(game.winner(), Some(Team::Muscovites), "repeating side did not lose");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . M . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . S . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the repetitions");
+    Ok(())
+}
+
+#[test]
+fn test_capture_diagram() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Brandubh, Team::Muscovites, "
+        + . . . . . +
+        S . . . . . .
+        . . . . M . .
+        S . . K# . . .
+        M . . . . . .
+        . . . . . . .
+        + . . . . . +
+    "));
+    let outcome = game.move_piece((4, 2), (0, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(0, 1), (0, 3)], "Swedes were not captured");
+    let after = Board::from_ascii(Rule::Brandubh, "
+        + . . . . . +
+        . . . . . . .
+        M . . . . . .
+        . . . K# . . .
+        M . . . . . .
+        . . . . . . .
+        + . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the captures");
+    Ok(())
+}
+
+#[test]
+fn test_escape_diagram() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Brandubh, Team::Swedes, "
+        + . . . . . +
+        . . . M . . .
+        . . . . . . .
+        M . . # . . K
+        . . . . . . .
+        . . . . . . .
+        + . . . . . +
+    "));
+    let outcome = game.move_piece((6, 3), (6, 0)).unwrap();
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Swedes)), "escape did not win");
+    let after = Board::from_ascii(Rule::Brandubh, "
+        + . . . . . K+
+        . . . M . . .
+        . . . . . . .
+        M . . # . . .
+        . . . . . . .
+        . . . . . . .
+        + . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the escape");
+    Ok(())
+}
+
+#[test]
+fn test_no_legal_moves_loses() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        M . . . . . . . .
+        K . . . # . . . .
+        M . . . . . . . .
+        . M . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    "));
+    let outcome = game.move_piece((1, 6), (1, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured against the edge");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "immobile side did not lose");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        M . . . . . . . .
+        K M . . # . . . .
+        M . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after shutting in the king");
+    Ok(())
+}
+
+#[test]
+fn test_encirclement_wins() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . M S M . . . .
+        . . . . M K# M . . . .
+        . . . . M . M . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . M . . . . +
+    "));
+    assert!(! game.is_encircled(), "ring with a gap counts as encirclement");
+
+    let outcome = game.move_piece((5, 10), (5, 7)).unwrap();
+    assert!(game.is_encircled(), "closed ring is not an encirclement");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "encirclement did not win");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . M S M . . . .
+        . . . . M K# M . . . .
+        . . . . M . M . . . .
+        . . . . . M . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after closing the ring");
+    Ok(())
+}
+
+#[test]
+fn test_encirclement_only_where_enabled() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Hnefatafl13, Team::Muscovites, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . M . . . . . .
+        . . . . . M K# M . . . . .
+        . . . . . M . M . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        + . . . . . M . . . . . +
+    "));
+    let outcome = game.move_piece((6, 12), (6, 8)).unwrap();
+    assert!(game.is_encircled(), "closed ring is not an encirclement");
+    assert_eq!(outcome.result, None, "encirclement won in a variant without it");
+    let after = Board::from_ascii(Rule::Hnefatafl13, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . M . . . . . .
+        . . . . . M K# M . . . . .
+        . . . . . M . M . . . . .
+        . . . . . . M . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        + . . . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after closing the ring");
+    Ok(())
+}
+
+#[test]
+fn test_exit_fort_wins() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Swedes, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . S . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        + . . . S K . S . . +
+    "));
+    assert!(! game.is_exit_fort(), "open fort counts as exit fort");
+
+    let outcome = game.move_piece((6, 7), (6, 9)).unwrap();
+    assert!(game.is_exit_fort(), "closed fort is not an exit fort");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Swedes)), "exit fort did not win");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S S . . . .
+        + . . . S K . S . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after closing the fort");
+    Ok(())
+}
+
+#[test]
+fn test_exit_fort_with_room() -> std::io::Result<()> {
+    let game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . S S S . . . .
+        . . . . S . S . . . .
+        + . . . S K S . . . +
+    "));
+    assert!(game.is_exit_fort(), "closed fort is not an exit fort");
+    Ok(())
+}
+
+#[test]
+fn test_exit_fort_breakable() -> std::io::Result<()> {
+    let game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        . . . . S . S . . . .
+        + . . . S K S . . . +
+    "));
+    assert!(! game.is_exit_fort(), "fort with a capturable piece counts");
+
+    let game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . S S S . . . .
+        + . . . S K S . . . +
+    "));
+    assert!(! game.is_exit_fort(), "fort where the king can not move counts");
+
+    let game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . S . S . . . .
+        + . . . S K S . . . +
+    "));
+    assert!(! game.is_exit_fort(), "fort open to a Muscovite counts");
+    Ok(())
+}
+
+#[test]
+fn test_exit_fort_only_where_enabled() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Hnefatafl13, Team::Swedes, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        M . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . # . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . S . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . S . . . . . .
+        + . . . . S K . S . . . +
+    "));
+    let outcome = game.move_piece((7, 8), (7, 11)).unwrap();
+    assert!(game.is_exit_fort(), "closed fort is not an exit fort");
+    assert_eq!(outcome.result, None, "exit fort won in a variant without it");
+    let after = Board::from_ascii(Rule::Hnefatafl13, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        M . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . # . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . S S . . . . .
+        + . . . . S K . S . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after closing the fort");
+    Ok(())
+}
+
+#[test]
+fn test_shieldwall_capture() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . M . . . . . . . .
+        . . . . . . . . . . .
+        . . . M M M . . . . .
+        + . . S S S M . . . +
+    "));
+    let outcome = game.move_piece((2, 7), (2, 10)).unwrap();
+    let mut captured = outcome.captured.clone();
+    captured.sort();
+    assert_eq!(captured, vec![(3, 10), (4, 10), (5, 10)], "shieldwall was not captured");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . M M M . . . . .
+        + . M . . . M . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the shieldwall");
+    Ok(())
+}
+
+#[test]
+fn test_shieldwall_against_corner_spares_king() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        S M . . . . . . . . .
+        K M . . . . . . . . .
+        S M . . . . . . . . .
+        . . . M . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 4), (0, 4)).unwrap();
+    let mut captured = outcome.captured.clone();
+    captured.sort();
+    assert_eq!(captured, vec![(0, 1), (0, 3)], "shieldwall against the corner was not captured");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . M . . . . . . . . .
+        K M . . . . . . . . .
+        . M . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "king was captured in a shieldwall");
+    Ok(())
+}
+
+#[test]
+fn test_shieldwall_needs_blocked_front() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . M . . . . . . . .
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        + . . S S M . . . . +
+    "));
+    let outcome = game.move_piece((2, 7), (2, 10)).unwrap();
+    assert_eq!(outcome.captured, vec![], "shieldwall with an open front was captured");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        + . M S S M . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after an open shieldwall");
+
+    let mut game = Game::from_variant(setup(Rule::Hnefatafl13, Team::Muscovites, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . K# . . . . . .
+        . . M . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . M M . . . . . . . .
+        + . . S S M . . . . . . +
+    "));
+    let outcome = game.move_piece((2, 7), (2, 12)).unwrap();
+    assert_eq!(outcome.captured, vec![], "shieldwall was captured in a variant without it");
+    let after = Board::from_ascii(Rule::Hnefatafl13, "
+        + . . . . . . . . . . . +
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . K# . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . . . . . . . . . . .
+        . . . M M . . . . . . . .
+        + . M S S M . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position without shieldwalls");
+    Ok(())
+}
+
+#[test]
+fn test_king_on_throne_needs_four_sides() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . M . . . .
+        . . . M K# . . . .
+        . . . . M . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . M . . S+
+    "));
+    game.move_piece((5, 8), (5, 6)).unwrap();
+    game.move_piece((8, 8), (8, 7)).unwrap();
+    assert_eq!(game.result, None, "king was captured on three sides");
+    let outcome = game.move_piece((5, 6), (5, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![(4, 4)], "king was not captured on four sides");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "capturing the king did not win");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . M . . . .
+        . . . M # M . . .
+        . . . . M . . . .
+        . . . . . . . . .
+        . . . . . . . . S
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_king_next_to_throne() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . . M . . . +
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . M K . M . .
+        . . . . # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . S+
+    "));
+    let outcome = game.move_piece((4, 0), (4, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on two sides next to the throne");
+    game.move_piece((8, 8), (8, 7)).unwrap();
+    let outcome = game.move_piece((6, 3), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(4, 3)], "king was not captured on three sides and the throne");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . . M . . . .
+        . . . M . M . . .
+        . . . . # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . S
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_king_away_from_throne_needs_two_sides() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . M K . . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . M . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 6), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(2, 2)], "king was not captured on two sides");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . M . M . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_king_capture_sides() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + M K . . . . . +
+        . . M . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . M # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . S+
+    "));
+    game.rules.king_capture_sides = 4;
+    let outcome = game.move_piece((3, 4), (3, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on the edge");
+
+    game.undo();
+    game.rules.king_capture_sides = 3;
+    let outcome = game.move_piece((3, 4), (3, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![(2, 0)], "king was not captured on three sides");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + M . M . . . . +
+        . . M . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        + . . . . . . . S+
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_unarmed_king() -> std::io::Result<()> {
+    let diagram = "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . K . . . . .
+        . . . M . . . . .
+        . . . . # . . . .
+        . . . . . . . . .
+        . . . S . . . . .
+        . . . . . . . M .
+        + . . . . . . . +
+    ";
+
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Swedes, diagram));
+    let outcome = game.move_piece((3, 6), (3, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 3)], "armed king did not help capturing");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . K . . . . .
+        . . . . . . . . .
+        . . . S # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . M .
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Swedes, diagram));
+    game.rules.king_armed = false;
+    let outcome = game.move_piece((3, 6), (3, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![], "unarmed king helped capturing");
+    let after = Board::from_ascii(Rule::Tablut, "
+        + . . . . . . . +
+        . . . . . . . . .
+        . . . K . . . . .
+        . . . M . . . . .
+        . . . S # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . M .
+        + . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position with an unarmed king");
+    Ok(())
+}
+
+#[test]
+fn test_throne_not_passable() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tablut, Team::Muscovites, "
+        + . . . . . . . +
+        . . . . M . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . # . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . K .
+        + . . . . . . . +
+    "));
+    game.rules.throne_passable = false;
+    assert_eq!(game.move_piece((4, 1), (4, 6)), Err(MoveError::PathBlocked));
+    assert_eq!(game.move_piece((4, 1), (4, 4)), Err(MoveError::RestrictedSquare));
+    assert!(game.move_piece((4, 1), (4, 3)).is_ok(), "move up to the throne was rejected");
+    Ok(())
+}
+
+#[test]
+fn test_first_move() -> std::io::Result<()> {
+    let rules = RuleSet { first_move: Team::Swedes, ..RuleSet::default() };
+    let mut game = Game::with_rules(Rule::Tablut, rules);
+    assert_eq!(game.side_to_move(), Team::Swedes, "Swedes do not move first");
+    assert_eq!(game.move_piece((3, 0), (3, 2)), Err(MoveError::NotYourPiece));
+    assert!(game.move_piece((2, 4), (2, 6)).is_ok(), "Swedes can not move first");
+    assert_eq!(game.side_to_move(), Team::Muscovites, "Muscovites do not move second");
+    Ok(())
+}
+
+#[test]
+fn test_linnaeus_edge_escape() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::LinnaeusTablut, Team::Swedes, "
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . K . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . M .
+        . . . . . . . . .
+    "));
+    let outcome = game.move_piece((2, 3), (2, 0)).unwrap();
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Swedes)), "king did not escape to the edge");
+    let after = Board::from_ascii(Rule::LinnaeusTablut, "
+        . . K . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . M .
+        . . . . . . . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the escape");
+
+    let mut game = Game::from_variant(setup(Rule::LinnaeusTablut, Team::Swedes, "
+        . . K . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . M .
+        . . . . . . . . .
+    "));
+    assert!(game.move_piece((2, 0), (0, 0)).is_ok(), "king can not move along the edge");
+
+    let mut game = Game::from_variant(setup(Rule::LinnaeusTablut, Team::Muscovites, "
+        . . . . . . . . .
+        . . . . . . . . .
+        M . . . . . . . .
+        . . . . . . . . .
+        . . . . K# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . S
+    "));
+    assert!(game.move_piece((0, 2), (0, 0)).is_ok(), "corner is restricted without corner escape");
+    Ok(())
+}
+
+#[test]
+fn test_linnaeus_king_capture() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::LinnaeusTablut, Team::Muscovites, "
+        . . . . . . . . .
+        . . . . . . . . .
+        . M K . . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . M . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+    "));
+    let outcome = game.move_piece((3, 6), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(2, 2)], "king was not captured on two sides");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "capturing the king did not win");
+    let after = Board::from_ascii(Rule::LinnaeusTablut, "
+        . . . . . . . . .
+        . . . . . . . . .
+        . M . M . . . . .
+        . . . . . . . . .
+        . . . . S# . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+
+    let mut game = Game::from_variant(setup(Rule::LinnaeusTablut, Team::Muscovites, "
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . M K# . . . .
+        . . . . . . . . .
+        . . . . . M . . .
+        . . . . . . . . .
+        . . . . . . . . S
+    "));
+    let outcome = game.move_piece((5, 6), (5, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on two sides on the throne");
+    let after = Board::from_ascii(Rule::LinnaeusTablut, "
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . M K# M . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . .
+        . . . . . . . . S
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position next to the king on the throne");
+    Ok(())
+}
+
+#[test]
+fn test_tawlbwrdd_edge_escape() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Tawlbwrdd, Team::Swedes, "
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+    "));
+    assert!(game.move_piece((5, 5), (5, 3)).is_ok(), "king can not leave the throne");
+    game.move_piece((0, 5), (1, 5)).unwrap();
+    let outcome = game.move_piece((5, 3), (10, 3)).unwrap();
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Swedes)), "king did not escape to the edge");
+    let after = Board::from_ascii(Rule::Tawlbwrdd, "
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . K
+        . . . . . . . . . . .
+        . M . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the escape");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_preset() -> std::io::Result<()> {
+    let rules = Rule::Copenhagen.rules();
+    assert!(rules.king_armed, "king is not armed");
+    assert_eq!(rules.king_capture_sides, 4, "king is not captured on four sides");
+    assert_eq!(rules.escape, Escape::Corner, "king does not escape to the corners");
+    assert!(!rules.throne_passable, "pieces may pass through the throne");
+    assert_eq!(rules.repetition, Repetition::PerpetualLoss, "perpetual repetition does not lose");
+    assert!(rules.edge_forts && rules.shieldwall && rules.encirclement, "extra rules are missing");
+    assert_eq!(Game::new(Rule::Copenhagen).board.size, 11, "board is not 11x11");
+
+    let generic = Rule::Hnefatafl11.rules();
+    assert_eq!(generic.repetition, Repetition::Draw, "Hnefatafl11 repetition is not a draw");
+    assert!(!generic.edge_forts && !generic.shieldwall && !generic.encirclement, "Hnefatafl11 has extra rules");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_king_four_sides() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . M . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . M K M . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 0), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on three sides");
+    game.move_piece((9, 9), (9, 8)).unwrap();
+    let outcome = game.move_piece((3, 7), (3, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 3)], "king was not captured on four sides");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "capturing the king did not win");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        . . M . M . . . . . .
+        . . . M . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_king_next_to_throne() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . M . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . M K M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(5, 4)], "king was not captured on three sides and the throne");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . M . M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_king_on_edge() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . M K M . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . M . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 5), (3, 1)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on the edge");
+    assert_eq!(outcome.result, None, "game ended with the king on the edge");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . M K M . . . . . +
+        . . . M . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position with the king on the edge");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_armed_king() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Swedes, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . K . . . . . . .
+        . . . M . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . S . . . . . . .
+        . . . . . . . . . M .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 8), (3, 5)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 4)], "king did not help capturing");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . K . . . . . . .
+        . . . . . . . . . . .
+        . . . S . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . M .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_throne_hostility() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . M . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(5, 4)], "Swede was not captured against the empty throne");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . M . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![], "Swede was captured against the occupied throne");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . S . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position next to the occupied throne");
+
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Swedes, "
+        + . . . . S . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(5, 4)], "Muscovite was not captured against the empty throne");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_corner_hostility() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + S . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . M . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((2, 3), (2, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![(1, 0)], "Swede was not captured against the corner");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . M . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+    Ok(())
+}
+
+#[test]
+fn test_copenhagen_throne_not_passable() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    assert_eq!(game.move_piece((5, 2), (5, 8)), Err(MoveError::PathBlocked));
+    assert_eq!(game.move_piece((5, 2), (5, 5)), Err(MoveError::RestrictedSquare));
+
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Swedes, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . M .
+        + . . . . . . . . . +
+    "));
+    assert!(game.move_piece((5, 3), (5, 8)).is_ok(), "king can not pass through the throne");
+    Ok(())
+}
+
+#[test]
+fn test_fetlar_king_capture() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Fetlar, Team::Muscovites, "
+        + . . M . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . M K M . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((3, 0), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![], "king was captured on three sides");
+    game.move_piece((9, 9), (9, 8)).unwrap();
+    let outcome = game.move_piece((3, 7), (3, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 3)], "king was not captured on four sides");
+    let after = Board::from_ascii(Rule::Fetlar, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        . . M . M . . . . . .
+        . . . M . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+
+    let mut game = Game::from_variant(setup(Rule::Fetlar, Team::Muscovites, "
+        + . . . . M . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . M K M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(5, 4)], "king was not captured on three sides and the throne");
+    let after = Board::from_ascii(Rule::Fetlar, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . M . M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
+}
+
+#[test]
+fn test_fetlar_hostile_squares() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Fetlar, Team::Muscovites, "
+        + S . . . M . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . M . . . . . . . .
+        . . . . . S . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((2, 3), (2, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![(1, 0)], "Swede was not captured against the corner");
+    game.move_piece((8, 8), (8, 7)).unwrap();
+    let outcome = game.move_piece((5, 0), (5, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![(5, 4)], "Swede was not captured against the empty throne");
+    let after = Board::from_ascii(Rule::Fetlar, "
+        + . M . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the captures");
+    Ok(())
+}
+
+#[test]
+fn test_fetlar_no_shieldwall_or_fort() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Fetlar, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . M . . . . . . . .
+        . . . . . . . . . . .
+        . . . M M . . . . . .
+        + . . S S M . . . . +
+    "));
+    let outcome = game.move_piece((2, 7), (2, 10)).unwrap();
+    assert_eq!(outcome.captured, vec![], "shieldwall was captured");
+    let after = Board::from_ascii(Rule::Fetlar, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . M M . . . . . .
+        + . M S S M . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position without shieldwalls");
+
+    let mut game = Game::from_variant(setup(Rule::Fetlar, Team::Swedes, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . S . . . .
+        . . . . . . . . . . .
+        . . . . . S . . . . .
+        + . . . S K . S . . +
+    "));
+    let outcome = game.move_piece((6, 7), (6, 9)).unwrap();
+    assert!(game.is_exit_fort(), "closed fort is not an exit fort");
+    assert_eq!(outcome.result, None, "exit fort won the game");
+    let after = Board::from_ascii(Rule::Fetlar, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . S S . . . .
+        + . . . S K . S . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after closing the fort");
+    Ok(())
+}
+
+#[test]
+fn test_commander_crosses_throne() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . M . . . . .
+        . . . . . c . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    assert!(game.move_piece((5, 2), (5, 8)).is_ok(), "commander can not pass the throne");
+    game.move_piece((9, 9), (9, 8)).unwrap();
+    assert!(game.move_piece((5, 8), (5, 5)).is_ok(), "commander can not enter the throne");
+    game.move_piece((9, 8), (9, 9)).unwrap();
+    assert_eq!(game.move_piece((5, 1), (5, 4)).err(), None);
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . M . . . . .
+        . . . . . c# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the commander moved");
+    Ok(())
+}
+
+#[test]
+fn test_prince_enters_corners() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Swedes, "
+        + . . P . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . S
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . M .
+        + . . . . . . . . . +
+    "));
+    assert_eq!(game.move_piece((10, 3), (10, 0)), Err(MoveError::RestrictedSquare));
+    let outcome = game.move_piece((3, 0), (0, 0)).unwrap();
+    assert_eq!(outcome.result, None, "prince won by reaching the corner");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        P+ . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . S
+        . . . . . . . . . . .
+        . . . . . K# . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . M .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the prince moved");
+    Ok(())
+}
+
+#[test]
+fn test_knight_enclosed_on_three_sides() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, "
+        + . . M . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . M N . . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . M . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    "));
+    let outcome = game.move_piece((4, 7), (4, 3)).unwrap();
+    assert_eq!(outcome.captured, vec![], "knight was captured on two sides");
+    game.move_piece((9, 9), (9, 8)).unwrap();
+    let outcome = game.move_piece((3, 0), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 3)], "knight was not captured on three sides");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . M . . . . . . .
+        . . M . M . . . . . .
+        . . . . . . . . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K S .
+        . . . . . . . . . . .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the knight");
+    Ok(())
+}
+
+#[test]
+fn test_capabilities() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Tablut);
+    assert!(game.capabilities(Piece::King).armed, "armed king is not armed");
+    game.rules.king_armed = false;
+    assert!(! game.capabilities(Piece::King).armed, "unarmed king is armed");
+    assert!(game.capabilities(Piece::Commander(Team::Swedes)).armed, "commander is unarmed with an unarmed king");
+    Ok(())
+}
+
+#[cfg(test)]
+const BERSERK_CHAIN: &str = "
+    + . . M . . . . . . +
+    . . . . . . . . . . .
+    . . . . . . . . S M .
+    . . . S . . S . . . .
+    . . . M . . M . . . .
+    . . . . . # . . . . .
+    . . . . . . . . . . .
+    . . . . . . . . . . .
+    . . . . . . . . K . .
+    . . . . . . . . . S .
+    + . . . . . . . . . +
+";
+
+#[test]
+fn test_berserk_captures_continue() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Berserk, Team::Muscovites, BERSERK_CHAIN));
+
+    let outcome = game.move_piece((3, 0), (3, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(3, 3)], "Swede was not captured");
+    assert!(! outcome.ends_turn, "capture ended the turn");
+    assert_eq!(game.continuing(), Some((3, 2)), "capturing piece can not move again");
+    assert_eq!(game.side_to_move(), Team::Muscovites, "turn passed after a capture");
+    assert_eq!(game.move_piece((3, 4), (2, 4)), Err(MoveError::NotContinuing));
+
+    let outcome = game.move_piece((3, 2), (6, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(6, 3)], "second Swede was not captured");
+    assert!(! outcome.ends_turn, "second capture ended the turn");
+    assert_eq!(game.move_piece((6, 2), (6, 1)), Err(MoveError::NoCapture), "piece moved again without capturing");
+    assert_eq!(game.legal_moves(), vec![Move { from: (6, 2), to: (7, 2) }], "legal moves are not the captures");
+
+    let outcome = game.move_piece((6, 2), (7, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(8, 2)], "third Swede was not captured");
+    assert!(outcome.ends_turn, "turn went on without another capture to make");
+    assert_eq!(game.continuing(), None, "piece can still move after the turn");
+    assert_eq!(game.side_to_move(), Team::Swedes, "turn did not pass");
+    assert_eq!(game.turn, 1, "sub-moves were counted as turns");
+    assert_eq!(game.history().len(), 3, "sub-moves were not recorded");
+    let after = Board::from_ascii(Rule::Berserk, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . M . M .
+        . . . . . . . . . . .
+        . . . M . . M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the berserk turn");
+    Ok(())
+}
+
+#[test]
+fn test_berserk_end_turn() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Berserk, Team::Muscovites, BERSERK_CHAIN));
+    assert!(! game.end_turn(), "turn ended without a capture");
+
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    assert!(game.end_turn(), "turn could not be ended after a capture");
+    assert_eq!(game.side_to_move(), Team::Swedes, "ending the turn did not pass it");
+    assert_eq!(game.continuing(), None, "piece can still move after ending the turn");
+    assert!(game.history()[0].ends_turn, "history does not record the end of the turn");
+
+    game.undo();
+    assert_eq!(game.side_to_move(), Team::Muscovites, "undo did not restore the turn");
+    assert_eq!(game.board.get_piece_at((3, 3)), Some(Piece::Swede), "undo did not restore the capture");
+
+    let outcome = game.redo().unwrap();
+    assert!(outcome.ends_turn, "redo did not end the turn again");
+    assert_eq!(game.side_to_move(), Team::Swedes, "redo did not pass the turn");
+    Ok(())
+}
+
+#[test]
+fn test_berserk_undo_sub_move() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::Berserk, Team::Muscovites, BERSERK_CHAIN));
+    let hash = game.hash();
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((3, 2), (6, 2)).unwrap();
+
+    game.undo();
+    assert_eq!(game.continuing(), Some((3, 2)), "undo did not restore the continuing piece");
+    assert_eq!(game.board.get_piece_at((6, 3)), Some(Piece::Swede), "undo did not restore the capture");
+    game.undo();
+    assert_eq!(game.continuing(), None, "undo left a continuing piece");
+    assert_eq!(game.hash(), hash, "undo did not restore the position");
+
+    let mut game = Game::from_variant(setup(Rule::Copenhagen, Team::Muscovites, BERSERK_CHAIN));
+    let outcome = game.move_piece((3, 0), (3, 2)).unwrap();
+    assert!(outcome.ends_turn, "capture without the berserk rule did not end the turn");
+    let after = Board::from_ascii(Rule::Copenhagen, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . M . . . . S M .
+        . . . . . . S . . . .
+        . . . M . . M . . . .
+        . . . . . # . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . K . .
+        . . . . . . . . . S .
+        + . . . . . . . . . +
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the capture");
+    Ok(())
+}
+
+#[test]
+fn test_ashton_tablut_preset() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::AshtonTablut);
+    assert_eq!(game.side_to_move(), Team::Swedes, "Muscovites move first");
+    assert_eq!(game.move_piece((1, 4), (1, 2)), Err(MoveError::NotYourPiece), "Muscovites moved first");
+    game.move_piece((4, 2), (2, 2)).unwrap();
+    assert_eq!(game.move_piece((4, 1), (4, 2)), Ok(MoveOutcome {
+        from: (4, 1),
+        to: (4, 2),
+        captured: vec![],
+        result: None,
+        ends_turn: true,
+    }), "Muscovite can not leave its camp");
+    Ok(())
+}
+
+#[test]
+fn test_ashton_tablut_camps() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::AshtonTablut, Team::Muscovites, "
+        . . . M* * * . . .
+        . . S . * . . . .
+        . . . . . . . . .
+        * . . . . . . . *
+        * * . . K# . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . S .
+        . . . * * * . . .
+    "));
+
+    assert_eq!(game.move_piece((3, 0), (3, 8)), Err(MoveError::RestrictedSquare), "Muscovite entered another camp");
+    game.move_piece((3, 0), (5, 0)).unwrap();
+    assert_eq!(game.move_piece((2, 1), (6, 1)), Err(MoveError::PathBlocked), "Swede crossed a camp");
+    game.move_piece((2, 1), (3, 1)).unwrap();
+    game.move_piece((5, 0), (5, 2)).unwrap();
+    game.move_piece((7, 7), (7, 6)).unwrap();
+    assert_eq!(game.move_piece((5, 2), (5, 0)), Err(MoveError::RestrictedSquare), "Muscovite re-entered its camp");
+
+    let targets = game.legal_moves_from((5, 2)).into_iter().map(|m| m.to).collect::<Vec<_>>();
+    assert!(targets.contains(&(5, 1)), "Muscovite can not move next to the camp");
+    assert!(! targets.contains(&(5, 0)), "Muscovite can re-enter its camp");
+    Ok(())
+}
+
+#[test]
+fn test_ashton_tablut_camp_hostility() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::AshtonTablut, Team::Muscovites, "
+        . . S * * M* . . .
+        . . . . * . . . .
+        . . . . . . . . .
+        * M . . . . S . *
+        * * . . K# . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . . .
+        . . . * * * . . .
+    "));
+
+    let outcome = game.move_piece((1, 3), (1, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![(2, 0)], "Swede was not captured against the camp");
+    let outcome = game.move_piece((6, 3), (6, 0)).unwrap();
+    assert_eq!(outcome.captured, vec![], "Muscovite was captured against its own camp");
+    let after = Board::from_ascii(Rule::AshtonTablut, "
+        . M . * * M* S . .
+        . . . . * . . . .
+        . . . . . . . . .
+        * . . . . . . . *
+        * * . . K# . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . . .
+        . . . * * * . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after the captures");
+    Ok(())
+}
+
+#[test]
+fn test_ashton_tablut_king_capture() -> std::io::Result<()> {
+    let mut game = Game::from_variant(setup(Rule::AshtonTablut, Team::Muscovites, "
+        . . . * * * . . .
+        . . . . * . . . .
+        . . M . . . . . .
+        * . . M K M . . *
+        * * . . # . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . S .
+        . . . * * * . . .
+    "));
+    let outcome = game.move_piece((2, 2), (4, 2)).unwrap();
+    assert_eq!(outcome.captured, vec![(4, 3)], "king was not captured by three and the throne");
+    let after = Board::from_ascii(Rule::AshtonTablut, "
+        . . . * * * . . .
+        . . . . * . . . .
+        . . . . M . . . .
+        * . . M . M . . *
+        * * . . # . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . S .
+        . . . * * * . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+
+    let mut game = Game::from_variant(setup(Rule::AshtonTablut, Team::Muscovites, "
+        . . . * * * . . .
+        . . . . * . . . .
+        . . . . . . . . .
+        * . . . . . . . *
+        * * K . # . . * *
+        * . . . . . . . *
+        . . . M . . . . .
+        . . . . * . . S .
+        . . . * * * . . .
+    "));
+    let outcome = game.move_piece((3, 6), (3, 4)).unwrap();
+    assert_eq!(outcome.captured, vec![(2, 4)], "king was not captured against a camp");
+    assert_eq!(outcome.result, Some(GameResult::Win(Team::Muscovites)), "capturing the king did not win");
+    let after = Board::from_ascii(Rule::AshtonTablut, "
+        . . . * * * . . .
+        . . . . * . . . .
+        . . . . . . . . .
+        * . . . . . . . *
+        * * . M # . . * *
+        * . . . . . . . *
+        . . . . . . . . .
+        . . . . * . . S .
+        . . . * * * . . .
+    ").unwrap();
+    assert_eq!(game.board.to_ascii(), after.to_ascii(), "wrong position after capturing the king");
+    Ok(())
 }
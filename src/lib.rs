@@ -0,0 +1,36 @@
+//! Rules engine for the tafl family of board games.
+//!
+//! The engine is independent of any front end. A [`Game`] owns a [`Board`]
+//! laid out according to a [`Rule`] and enforces the movement, capture and
+//! victory rules of that variant. Variants beyond the built-in ones can be
+//! read from text files, see [`variant`]. Moves can be written and read in
+//! algebraic notation, see [`notation`], and whole games saved and loaded
+//! as records, see [`record`].
+//!
+//! ```
+//! use tafl::{Game, Rule};
+//!
+//! let mut game = Game::new(Rule::Tablut);
+//! let outcome = game.move_piece((4, 1), (2, 1)).unwrap();
+//! assert!(outcome.captured.is_empty());
+//! assert_eq!(game.side_to_move(), tafl::Team::Swedes);
+//! ```
+
+pub mod bitboard;
+pub mod board;
+pub mod game;
+pub mod notation;
+pub mod piece;
+pub mod record;
+pub mod rules;
+pub mod variant;
+mod zobrist;
+
+pub use bitboard::{BitBoard, Bits};
+pub use board::{Board, Square, Status};
+pub use game::{Coord, Game, GameResult, HistoryEntry, Move, MoveError, MoveOutcome, Rule};
+pub use notation::{NotatedMove, NotationError};
+pub use piece::{Capabilities, Capture, Piece, Team};
+pub use record::{Record, RecordError};
+pub use rules::{Escape, Repetition, RuleSet};
+pub use variant::{Variant, VariantError};
@@ -1,20 +1,19 @@
 mod app;
 mod event;
-mod game;
 mod ui;
-mod piece;
-mod board;
 
 
 use std::{
     error::Error,
+    fs,
     io::{self},
-    time::Duration,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use app::{App};
 use event::{Event, Events};
-use game::{Rule};
+use tafl::{Game, Record, Rule, Variant};
 use structopt::StructOpt;
 use termion::{
     raw::IntoRawMode,
@@ -24,31 +23,90 @@ use tui::{backend::TermionBackend, Terminal};
 
 #[derive(Debug, StructOpt)]
 struct Opt {
-    #[structopt(short, long, possible_values= &Rule::variants(), default_value="Tablut")]
+    #[structopt(short, long, possible_values = Rule::variants(), default_value="Tablut")]
     pub rule: Rule,
+
+    /// Play a variant defined in a file instead of a built-in rule.
+    #[structopt(long, parse(from_os_str))]
+    pub variant_file: Option<PathBuf>,
+
+    /// Continue a game from a saved record.
+    #[structopt(long, parse(from_os_str))]
+    pub load: Option<PathBuf>,
+
+    /// Where to save the game record, by default the loaded record or
+    /// game.tafl.
+    #[structopt(long, parse(from_os_str))]
+    pub save: Option<PathBuf>,
+}
+
+
+/// Today's date as `YYYY.MM.DD`, for the record of a new game.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+
+    // days since 1970-01-01 to a date, after Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
 }
 
 
 fn main() -> Result<(), Box<dyn Error>> {
     let opt = Opt::from_args();
 
+    let variant = match &opt.variant_file {
+        Some(path) => {
+            let variant: Variant = fs::read_to_string(path)?
+                .parse()
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            Some(variant)
+        },
+        None => None,
+    };
+
+    let (record, game) = match &opt.load {
+        Some(path) => Record::load(&fs::read_to_string(path)?, variant)
+            .map_err(|err| format!("{}: {}", path.display(), err))?,
+        None => {
+            let game = match variant {
+                Some(variant) => Game::from_variant(variant),
+                None => Game::new(opt.rule),
+            };
+            let record = Record { date: today(), ..Record::new(&game) };
+            (record, game)
+        },
+    };
+    let save_path = opt.save
+        .or(opt.load)
+        .unwrap_or_else(|| PathBuf::from("game.tafl"));
+
     let events = Events::new(Duration::from_millis(250));
 
     let stdout = io::stdout().into_raw_mode()?;
     let backend = TermionBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new("Tafl".into(), opt.rule);
+    let mut app = App::new("Tafl".into(), game, record, save_path);
 
     loop {
         terminal.draw(|f| ui::draw(f, &mut app))?;
 
-        match events.next()? {
-            Event::Input(key) => app.on_key(key),
-            _ => {},
+        if let Event::Input(key) = events.next()? {
+            app.on_key(key);
         }
 
-        if app.game.winner != None {
+        if app.quit {
             _ = terminal.clear();
             break;
         }
@@ -0,0 +1,271 @@
+//! Algebraic notation for moves.
+//!
+//! Squares are written as a file letter and a rank number, like `d1`. Files
+//! run from `a` on the left edge up to `s` on 19x19 boards, ranks from `1` on
+//! the bottom edge, so the file is the column of a [`Coord`] and the rank
+//! counts its row from the bottom.
+//!
+//! A move is written as the two squares joined by `-`, like `d1-d4`. Each
+//! captured piece adds its square with an `x`, as in `d1-d4xd5xc4`, and a
+//! move that wins the game ends in `#`.
+
+use std::{error::Error, fmt::{self, Display}};
+
+use super::game::{Coord, GameResult, HistoryEntry, Move, MoveOutcome};
+
+
+/// The letters of the files, from the left edge.
+const FILES: &str = "abcdefghijklmnopqrs";
+
+
+/// A move in notation, with the captures and win it is annotated with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotatedMove {
+    pub mv: Move,
+    /// Squares from which pieces were captured by the move.
+    pub captured: Vec<Coord>,
+    /// Whether the move won the game.
+    pub wins: bool,
+}
+
+impl NotatedMove {
+    /// Read a move written for a board of `size`.
+    pub fn parse(text: &str, size: u16) -> Result<Self, NotationError> {
+        let mut parser = Parser { text, position: 0, size };
+        parser.skip_whitespace();
+        if parser.peek().is_none() {
+            return Err(parser.error("expected a move, found nothing"));
+        }
+
+        let from = parser.square()?;
+        match parser.next() {
+            Some('-') => {},
+            Some(c) => return Err(parser.error_before(format!("expected `-` after the first square, found `{}`", c))),
+            None => return Err(parser.error("expected `-` after the first square, found the end")),
+        }
+        let to = parser.square()?;
+
+        let mut captured = Vec::new();
+        let mut wins = false;
+        while let Some(c) = parser.next() {
+            match c {
+                'x' if ! wins => captured.push(parser.square()?),
+                '#' if ! wins => wins = true,
+                c if c.is_whitespace() => {
+                    parser.skip_whitespace();
+                    if let Some(c) = parser.peek() {
+                        parser.next();
+                        return Err(parser.error_before(format!("expected the end of the move, found `{}`", c)));
+                    }
+                },
+                c if wins => return Err(parser.error_before(format!("expected the end of the move after `#`, found `{}`", c))),
+                c => return Err(parser.error_before(format!("expected `x`, `#` or the end of the move, found `{}`", c))),
+            }
+        }
+
+        Ok(Self { mv: Move { from, to }, captured, wins })
+    }
+
+    /// Write the move for a board of `size`.
+    pub fn format(&self, size: u16) -> String {
+        let mut text = format_move(self.mv, size);
+        for &at in &self.captured {
+            text.push('x');
+            text.push_str(&format_square(at, size));
+        }
+        if self.wins {
+            text.push('#');
+        }
+        text
+    }
+}
+
+impl From<&MoveOutcome> for NotatedMove {
+    fn from(outcome: &MoveOutcome) -> Self {
+        Self {
+            mv: Move { from: outcome.from, to: outcome.to },
+            captured: outcome.captured.clone(),
+            wins: matches!(outcome.result, Some(GameResult::Win(_))),
+        }
+    }
+}
+
+impl From<&HistoryEntry> for NotatedMove {
+    fn from(entry: &HistoryEntry) -> Self {
+        Self {
+            mv: entry.mv,
+            captured: entry.captured.iter().map(|&(at, _)| at).collect(),
+            wins: matches!(entry.result, Some(GameResult::Win(_))),
+        }
+    }
+}
+
+
+/// Why a move or square could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError {
+    /// The character the error was found at, counting from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl Error for NotationError {}
+
+
+/// Write the square `at` of a board of `size`, like `d1`.
+pub fn format_square(at: Coord, size: u16) -> String {
+    let file = FILES.as_bytes()[at.0] as char;
+    format!("{}{}", file, size as usize - at.1)
+}
+
+/// Write `mv` without annotations, like `d1-d4`.
+pub fn format_move(mv: Move, size: u16) -> String {
+    format!("{}-{}", format_square(mv.from, size), format_square(mv.to, size))
+}
+
+/// Read a single square of a board of `size`, like `d1`.
+pub fn parse_square(text: &str, size: u16) -> Result<Coord, NotationError> {
+    let mut parser = Parser { text, position: 0, size };
+    let at = parser.square()?;
+    if let Some(c) = parser.next() {
+        return Err(parser.error_before(format!("expected the end of the square, found `{}`", c)));
+    }
+    Ok(at)
+}
+
+
+struct Parser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character.
+    position: usize,
+    size: u16,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    /// An error at the next character.
+    fn error(&self, message: impl Into<String>) -> NotationError {
+        let column = self.text[..self.position].chars().count() + 1;
+        NotationError { column, message: message.into() }
+    }
+
+    /// An error at the character just read.
+    fn error_before(&self, message: impl Into<String>) -> NotationError {
+        let column = self.text[..self.position].chars().count();
+        NotationError { column, message: message.into() }
+    }
+
+    fn square(&mut self) -> Result<Coord, NotationError> {
+        let size = self.size as usize;
+        let last = FILES.as_bytes()[size - 1] as char;
+
+        let x = match self.next() {
+            Some(c) => match FILES[..size].find(c) {
+                Some(x) => x,
+                None => return Err(self.error_before(format!("expected a file from a to {}, found `{}`", last, c))),
+            },
+            None => return Err(self.error(format!("expected a file from a to {}, found the end", last))),
+        };
+
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.next();
+        }
+        let digits = &self.text[start..self.position];
+        if digits.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => self.error(format!("expected a rank from 1 to {}, found `{}`", size, c)),
+                None => self.error(format!("expected a rank from 1 to {}, found the end", size)),
+            });
+        }
+        match digits.parse::<usize>() {
+            Ok(rank) if (1..=size).contains(&rank) => Ok((x, size - rank)),
+            _ => Err(NotationError {
+                column: self.text[..start].chars().count() + 1,
+                message: format!("expected a rank from 1 to {}, found {}", size, digits),
+            }),
+        }
+    }
+}
+
+
+#[cfg(test)]
+use super::game::{Game, Rule};
+
+#[test]
+fn test_squares() -> std::io::Result<()> {
+    assert_eq!(format_square((0, 8), 9), "a1", "bottom left corner");
+    assert_eq!(format_square((8, 0), 9), "i9", "top right corner");
+    assert_eq!(format_square((18, 0), 19), "s19", "top right corner of 19x19");
+    assert_eq!(parse_square("d1", 9), Ok((3, 8)));
+    assert_eq!(parse_square("k11", 11), Ok((10, 0)));
+    for x in 0..13 {
+        for y in 0..13 {
+            assert_eq!(parse_square(&format_square((x, y), 13), 13), Ok((x, y)), "{:?} does not round trip", (x, y));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_move() -> std::io::Result<()> {
+    let parsed = NotatedMove::parse("d1-d4", 9).unwrap();
+    assert_eq!(parsed.mv, Move { from: (3, 8), to: (3, 5) });
+    assert!(parsed.captured.is_empty() && ! parsed.wins, "plain move is annotated");
+
+    let parsed = NotatedMove::parse(" d1-d4xd5xc4# ", 9).unwrap();
+    assert_eq!(parsed.captured, vec![(3, 4), (2, 5)], "captures were not read");
+    assert!(parsed.wins, "win marker was not read");
+    assert_eq!(parsed.format(9), "d1-d4xd5xc4#", "move does not round trip");
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() -> std::io::Result<()> {
+    let error = |text: &str| NotatedMove::parse(text, 9).err().map(|err| (err.column, err.message));
+    assert_eq!(error(""), Some((1, "expected a move, found nothing".to_string())));
+    assert_eq!(error("j1-d4"), Some((1, "expected a file from a to i, found `j`".to_string())));
+    assert_eq!(error("d-d4"), Some((2, "expected a rank from 1 to 9, found `-`".to_string())));
+    assert_eq!(error("d10-d4"), Some((2, "expected a rank from 1 to 9, found 10".to_string())));
+    assert_eq!(error("d1d4"), Some((3, "expected `-` after the first square, found `d`".to_string())));
+    assert_eq!(error("d1-"), Some((4, "expected a file from a to i, found the end".to_string())));
+    assert_eq!(error("d1-d4+"), Some((6, "expected `x`, `#` or the end of the move, found `+`".to_string())));
+    assert_eq!(error("d1-d4#xd5"), Some((7, "expected the end of the move after `#`, found `x`".to_string())));
+    assert_eq!(error("d1-d4 d5"), Some((7, "expected the end of the move, found `d`".to_string())));
+    Ok(())
+}
+
+#[test]
+fn test_notate_game() -> std::io::Result<()> {
+    let mut game = Game::new(Rule::Brandubh);
+    let size = game.board.size;
+    let outcome = game.move_piece((3, 1), (1, 1)).unwrap();
+    assert_eq!(NotatedMove::from(&outcome).format(size), "d6-b6", "move was written wrongly");
+
+    let parsed = NotatedMove::parse("d5-f5", size).unwrap();
+    let outcome = game.move_piece(parsed.mv.from, parsed.mv.to).unwrap();
+    assert_eq!(NotatedMove::from(&outcome), parsed, "parsed move differs from the one played");
+    assert_eq!(NotatedMove::from(&game.history()[1]), parsed, "history differs from the move played");
+    Ok(())
+}
@@ -1,37 +1,150 @@
 use std::{fmt::{self, Display}};
 
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+/// The two sides of a game.
+///
+/// The Muscovites are the attackers, the Swedes defend the king.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum Team {
     Muscovites,
     Swedes,
 }
 
+impl Team {
+    /// The other team.
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::Muscovites => Team::Swedes,
+            Team::Swedes => Team::Muscovites,
+        }
+    }
+}
+
+
+/// How a piece is captured.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Capture {
+    /// Between two enemies, or an enemy and a hostile square.
+    Custodial,
+    /// By enemies or hostile squares on at least this many sides.
+    Enclosed(u8),
+    /// Like the king, following the king capture options of the `RuleSet`.
+    Royal,
+}
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+
+/// What a kind of piece is allowed to do.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Capabilities {
+    /// Whether the piece may stop on the throne and always pass through it.
+    pub enters_throne: bool,
+    /// Whether the piece may stop on the corners.
+    pub enters_corners: bool,
+    /// Whether the piece takes part in capturing enemies.
+    pub armed: bool,
+    /// Whether the game is decided by this piece: the Swedes win when it
+    /// escapes and lose when it is captured.
+    pub royal: bool,
+    pub capture: Capture,
+}
+
+
+/// A piece on the board.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum Piece {
     King,
     Muscovite,
     Swede,
+    /// A warrior that may pass and occupy the throne.
+    Commander(Team),
+    /// A warrior that has to be enclosed on three sides to be captured.
+    Knight(Team),
+    /// A Swede that may enter the corners, without winning by it.
+    Prince,
 }
 
 impl Display for Piece {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match *self {
-            Piece::Swede => "S",
-            Piece::Muscovite => "M",
-            Piece::King => "K",
-        };
-        write!(f, "{}", s)
+        write!(f, "{}", self.symbol())
     }
 }
 
 impl Piece {
+    /// All kinds of pieces.
+    pub const ALL: [Piece; 8] = [
+        Piece::King,
+        Piece::Muscovite,
+        Piece::Swede,
+        Piece::Commander(Team::Muscovites),
+        Piece::Commander(Team::Swedes),
+        Piece::Knight(Team::Muscovites),
+        Piece::Knight(Team::Swedes),
+        Piece::Prince,
+    ];
+
+    /// The team this piece plays for.
     pub fn get_team(self) -> Team {
         match self {
             Piece::King => Team::Swedes,
             Piece::Muscovite => Team::Muscovites,
             Piece::Swede => Team::Swedes,
+            Piece::Commander(team) | Piece::Knight(team) => team,
+            Piece::Prince => Team::Swedes,
+        }
+    }
+
+    /// The letter of this piece, upper case for the Swedes' commander and
+    /// knight and lower case for the Muscovites'.
+    pub fn symbol(self) -> char {
+        match self {
+            Piece::King => 'K',
+            Piece::Muscovite => 'M',
+            Piece::Swede => 'S',
+            Piece::Commander(Team::Muscovites) => 'c',
+            Piece::Commander(Team::Swedes) => 'C',
+            Piece::Knight(Team::Muscovites) => 'n',
+            Piece::Knight(Team::Swedes) => 'N',
+            Piece::Prince => 'P',
+        }
+    }
+
+    /// The piece written as `symbol`, if any.
+    pub fn from_symbol(symbol: char) -> Option<Piece> {
+        Piece::ALL.into_iter().find(|piece| piece.symbol() == symbol)
+    }
+
+    /// What this kind of piece is allowed to do. The rules of a game may
+    /// restrict them further, see `Game::capabilities`.
+    pub fn capabilities(self) -> Capabilities {
+        let warrior = Capabilities {
+            enters_throne: false,
+            enters_corners: false,
+            armed: true,
+            royal: false,
+            capture: Capture::Custodial,
+        };
+        match self {
+            Piece::King => Capabilities {
+                enters_throne: true,
+                enters_corners: true,
+                royal: true,
+                capture: Capture::Royal,
+                ..warrior
+            },
+            Piece::Muscovite | Piece::Swede => warrior,
+            Piece::Commander(_) => Capabilities { enters_throne: true, ..warrior },
+            Piece::Knight(_) => Capabilities { capture: Capture::Enclosed(3), ..warrior },
+            Piece::Prince => Capabilities { enters_corners: true, ..warrior },
         }
     }
 }
+
+
+#[test]
+fn test_symbols() -> std::io::Result<()> {
+    for piece in Piece::ALL {
+        assert_eq!(Piece::from_symbol(piece.symbol()), Some(piece), "{:?} does not round trip", piece);
+    }
+    assert_eq!(Piece::from_symbol('.'), None, "empty square is a piece");
+    Ok(())
+}
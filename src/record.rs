@@ -0,0 +1,449 @@
+//! Game records in a text format modelled on PGN.
+//!
+//! A record starts with tag pairs, one per line, followed by the moves in
+//! [`notation`](super::notation), one line per pair of turns:
+//!
+//! ```text
+//! [Variant "Brandubh"]
+//! [Muscovites "Alice"]
+//! [Swedes "Bob"]
+//! [Date "2024.05.01"]
+//! [TimeControl "-"]
+//! [Result "*"]
+//!
+//! 1. d6-b6 d5-f5
+//! 2. b6-b5 f5-f6
+//! ```
+//!
+//! The result is `Muscovites` or `Swedes` for the winner, `Draw`, or `*` for
+//! a game without a result. Unknown players and dates are written as `?`.
+//! Under the berserk rule the moves of a turn simply follow each other, a
+//! move of another piece ends the turn of the piece that captured. A turn
+//! that ends on a capture is closed by `e`, as the piece might have gone
+//! on. When ending the turn decides the game, its last move is marked `#`.
+
+use std::{error::Error, fmt::{self, Display}};
+
+use super::game::{Game, GameResult, Rule};
+use super::notation::NotatedMove;
+use super::piece::Team;
+use super::variant::Variant;
+
+
+/// The tag pairs of a game record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The name of the variant, which has to match the game it is loaded into.
+    pub variant: String,
+    /// The player of the Muscovites.
+    pub muscovites: String,
+    /// The player of the Swedes.
+    pub swedes: String,
+    /// The date the game was played, as `YYYY.MM.DD`.
+    pub date: String,
+    pub time_control: String,
+    /// The result, which may also be decided by resignation or time.
+    pub result: Option<GameResult>,
+}
+
+
+/// Why a game record could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// The line the error was found on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for RecordError {}
+
+
+/// The token closing a berserk turn that ends on a capture.
+const END_TURN: &str = "e";
+
+fn error(line: usize, message: impl Into<String>) -> RecordError {
+    RecordError { line, message: message.into() }
+}
+
+fn format_result(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::Win(Team::Muscovites)) => "Muscovites",
+        Some(GameResult::Win(Team::Swedes)) => "Swedes",
+        Some(GameResult::Draw) => "Draw",
+        None => "*",
+    }
+}
+
+fn parse_result(line: usize, value: &str) -> Result<Option<GameResult>, RecordError> {
+    match value {
+        "Muscovites" => Ok(Some(GameResult::Win(Team::Muscovites))),
+        "Swedes" => Ok(Some(GameResult::Win(Team::Swedes))),
+        "Draw" => Ok(Some(GameResult::Draw)),
+        "*" => Ok(None),
+        _ => Err(error(line, format!("expected Muscovites, Swedes, Draw or *, found `{}`", value))),
+    }
+}
+
+/// Parse a tag pair like `[Variant "Tablut"]`.
+fn parse_tag(line: usize, text: &str) -> Result<(&str, &str), RecordError> {
+    let expected = || error(line, "expected a tag pair like `[Variant \"Tablut\"]`");
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .ok_or_else(expected)?;
+    let (key, value) = inner.trim().split_once(char::is_whitespace).ok_or_else(expected)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(expected)?;
+    Ok((key, value))
+}
+
+/// The whitespace-separated tokens of `text` and the columns they start
+/// at, counting from 1.
+fn tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace().map(move |token| {
+        let offset = token.as_ptr() as usize - text.as_ptr() as usize;
+        (text[..offset].chars().count() + 1, token)
+    })
+}
+
+
+impl Record {
+    /// The tags of `game`, with unknown players and date.
+    pub fn new(game: &Game) -> Self {
+        Self {
+            variant: game.variant.clone(),
+            muscovites: "?".to_string(),
+            swedes: "?".to_string(),
+            date: "????.??.??".to_string(),
+            time_control: "-".to_string(),
+            result: game.result,
+        }
+    }
+
+    /// Write the record of `game` with these tags. The result of the game
+    /// takes precedence over the `result` tag.
+    pub fn write(&self, game: &Game) -> String {
+        let tags = [
+            ("Variant", game.variant.as_str()),
+            ("Muscovites", &self.muscovites),
+            ("Swedes", &self.swedes),
+            ("Date", &self.date),
+            ("TimeControl", &self.time_control),
+            ("Result", format_result(game.result.or(self.result))),
+        ];
+        let mut text = tags
+            .iter()
+            .map(|(key, value)| format!("[{} \"{}\"]\n", key, value))
+            .collect::<String>();
+        text.push('\n');
+
+        let size = game.board.size;
+        let mut turn = 0;
+        let mut starts_turn = true;
+        for entry in game.history() {
+            if starts_turn && turn % 2 == 0 {
+                if turn > 0 {
+                    text.push('\n');
+                }
+                text.push_str(&format!("{}.", turn / 2 + 1));
+            }
+            text.push(' ');
+            text.push_str(&NotatedMove::from(entry).format(size));
+            if game.rules.berserk && entry.ends_turn && ! entry.captured.is_empty() && entry.result.is_none() {
+                text.push(' ');
+                text.push_str(END_TURN);
+            }
+            starts_turn = entry.ends_turn;
+            if entry.ends_turn {
+                turn += 1;
+            }
+        }
+        if ! game.history().is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Read a record and replay its moves.
+    ///
+    /// The game is played as `variant`, or the built-in variant named in the
+    /// record if it is `None`. Illegal moves, moves whose annotations do not
+    /// match the game and a result the moves contradict are rejected.
+    pub fn load(text: &str, variant: Option<Variant>) -> Result<(Record, Game), RecordError> {
+        let mut record = Record {
+            variant: String::new(),
+            muscovites: "?".to_string(),
+            swedes: "?".to_string(),
+            date: "????.??.??".to_string(),
+            time_control: "-".to_string(),
+            result: None,
+        };
+        let mut variant_line = None;
+        let mut result_line = None;
+
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line)).peekable();
+        while let Some(&(line, text)) = lines.peek() {
+            let text = text.trim();
+            if ! text.is_empty() && ! text.starts_with('[') {
+                break;
+            }
+            lines.next();
+            if text.is_empty() {
+                continue;
+            }
+            let (key, value) = parse_tag(line, text)?;
+            match key {
+                "Variant" => {
+                    record.variant = value.to_string();
+                    variant_line = Some(line);
+                },
+                "Muscovites" => record.muscovites = value.to_string(),
+                "Swedes" => record.swedes = value.to_string(),
+                "Date" => record.date = value.to_string(),
+                "TimeControl" => record.time_control = value.to_string(),
+                "Result" => {
+                    record.result = parse_result(line, value)?;
+                    result_line = Some(line);
+                },
+                _ => {},
+            }
+        }
+
+        let variant_line = variant_line.ok_or_else(|| error(1, "missing tag `Variant`"))?;
+        let variant = match variant {
+            Some(variant) if variant.name == record.variant => variant,
+            Some(variant) => {
+                return Err(error(variant_line, format!("the record is of {}, not {}", record.variant, variant.name)));
+            },
+            None => record.variant
+                .parse::<Rule>()
+                .map_err(|_| error(variant_line, format!("unknown variant `{}`", record.variant)))?
+                .variant(),
+        };
+
+        let mut game = Game::from_variant(variant);
+        let size = game.board.size;
+        for (line, text) in lines {
+            for (column, token) in tokens(text) {
+                if token == END_TURN {
+                    game.end_turn();
+                    continue;
+                }
+                if let Some(number) = token.strip_suffix('.') {
+                    game.end_turn();
+                    let expected = game.turn / 2 + 1;
+                    if game.side_to_move() != game.rules.first_move || number != expected.to_string() {
+                        return Err(error(line, format!("expected move number {}., found `{}`", expected, token)));
+                    }
+                    continue;
+                }
+
+                let notated = NotatedMove::parse(token, size)
+                    .map_err(|err| error(line, format!("column {}: {}", column + err.column - 1, err.message)))?;
+                if game.continuing().is_some_and(|at| at != notated.mv.from) {
+                    game.end_turn();
+                }
+                let outcome = game
+                    .move_piece(notated.mv.from, notated.mv.to)
+                    .map_err(|err| error(line, format!("illegal move `{}`: {}", token, err)))?;
+                let mut played = NotatedMove::from(&outcome);
+                if notated.wins && ! played.wins && game.end_turn() {
+                    played = game.history().last().map_or(played, NotatedMove::from);
+                }
+                if played != notated {
+                    return Err(error(line, format!("`{}` does not match the move played, `{}`", token, played.format(size))));
+                }
+            }
+        }
+
+        if game.result.is_some() && game.result != record.result {
+            return Err(error(
+                result_line.unwrap_or(1),
+                format!("the moves end in the result {}, not {}", format_result(game.result), format_result(record.result)),
+            ));
+        }
+
+        Ok((record, game))
+    }
+}
+
+
+#[cfg(test)]
+use super::variant::setup;
+
+#[cfg(test)]
+const RECORD: &str = "[Variant \"Brandubh\"]
+[Muscovites \"Alice\"]
+[Swedes \"Bob\"]
+[Date \"2024.05.01\"]
+[TimeControl \"-\"]
+[Result \"*\"]
+
+1. d6-b6 d5-f5
+2. b6-b5 f5-f6
+";
+
+#[test]
+fn test_load_record() -> std::io::Result<()> {
+    let (record, game) = Record::load(RECORD, None).unwrap();
+    assert_eq!(record.variant, "Brandubh", "wrong variant");
+    assert_eq!(record.muscovites, "Alice", "wrong player");
+    assert_eq!(record.date, "2024.05.01", "wrong date");
+    assert_eq!(record.result, None, "unfinished game has a result");
+    assert_eq!(game.history().len(), 4, "moves were not replayed");
+    assert_eq!(game.board.get_piece_at((1, 2)), Some(super::piece::Piece::Muscovite), "last Muscovite move is missing");
+    assert_eq!(record.write(&game), RECORD, "record does not round trip");
+    Ok(())
+}
+
+#[test]
+fn test_record_errors() -> std::io::Result<()> {
+    let error = |text: &str| Record::load(text, None).err().map(|err| err.to_string());
+    assert_eq!(
+        error(&RECORD.replace("f5-f6", "f5-e6")),
+        Some("line 9: illegal move `f5-e6`: pieces can not move diagonally".to_string()));
+    assert_eq!(
+        error(&RECORD.replace("b6-b5", "b6-z5")),
+        Some("line 9: column 7: expected a file from a to g, found `z`".to_string()));
+    assert_eq!(
+        error(&RECORD.replace("b6-b5", "b6-b5xb4")),
+        Some("line 9: `b6-b5xb4` does not match the move played, `b6-b5`".to_string()));
+    assert_eq!(
+        error(&RECORD.replace("2.", "3.")),
+        Some("line 9: expected move number 2., found `3.`".to_string()));
+    assert_eq!(
+        error(&RECORD.replace("Brandubh", "Chess")),
+        Some("line 1: unknown variant `Chess`".to_string()));
+    assert_eq!(
+        error(&RECORD.replace("[Swedes \"Bob\"]", "[Swedes Bob]")),
+        Some("line 3: expected a tag pair like `[Variant \"Tablut\"]`".to_string()));
+
+    let custom = Variant { name: "Custom".to_string(), ..Rule::Brandubh.variant() };
+    let err = Record::load(RECORD, Some(custom)).err().map(|err| err.to_string());
+    assert_eq!(err, Some("line 1: the record is of Brandubh, not Custom".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_record_result() -> std::io::Result<()> {
+    let variant = setup(Rule::Brandubh, Team::Muscovites, "
+        + . . . . . +
+        . . . M . . .
+        . . . . . . .
+        M . . K# . . .
+        . . . . . . .
+        . . . . . . .
+        + . . . . . +
+    ");
+    let mut game = Game::from_variant(variant.clone());
+    game.move_piece((3, 1), (1, 1)).unwrap();
+    game.move_piece((3, 3), (6, 3)).unwrap();
+
+    let mut record = Record::new(&game);
+    record.result = Some(GameResult::Win(Team::Swedes));
+    let text = record.write(&game);
+    assert!(text.contains("[Result \"Swedes\"]"), "result tag was not written");
+    let (loaded, _) = Record::load(&text, Some(variant.clone())).unwrap();
+    assert_eq!(loaded.result, Some(GameResult::Win(Team::Swedes)), "resignation was not kept");
+
+    game.move_piece((1, 1), (1, 2)).unwrap();
+    game.move_piece((6, 3), (6, 0)).unwrap();
+    let text = record.write(&game);
+    assert!(text.ends_with("1. d6-b6 d4-g4\n2. b6-b5 g4-g7#\n"), "win was not marked:\n{}", text);
+    let err = Record::load(&text.replace("Swedes\"]", "Draw\"]"), Some(variant)).err();
+    assert_eq!(err.map(|err| err.line), Some(6), "contradicting result was not reported on its tag");
+    Ok(())
+}
+
+#[cfg(test)]
+const BERSERK: &str = "
+    + . . M . . . . . . +
+    . . . . . . . . . . .
+    . . . . . . . . . . .
+    . . . S . . S . . . .
+    . . . M . . M . . . .
+    . . . . . K# . . . . .
+    . . . . . . . . . . .
+    . . . . . . . . . . .
+    . . . . . . . . . . .
+    . . . . . . . . . S .
+    + . . . . . . . . . +
+";
+
+#[test]
+fn test_record_berserk_turns() -> std::io::Result<()> {
+    let variant = setup(Rule::Berserk, Team::Muscovites, BERSERK);
+    let mut game = Game::from_variant(variant.clone());
+    game.move_piece((3, 0), (3, 2)).unwrap();
+    game.move_piece((3, 2), (6, 2)).unwrap();
+    game.end_turn();
+    game.move_piece((9, 9), (9, 8)).unwrap();
+
+    let text = Record::new(&game).write(&game);
+    assert!(text.ends_with("1. d11-d9xd8 d9-g9xg8 e j2-j3\n"), "berserk turn was not written:\n{}", text);
+    let (_, loaded) = Record::load(&text, Some(variant)).unwrap();
+    assert_eq!(loaded.history(), game.history(), "berserk turn was not replayed");
+    assert_eq!(loaded.side_to_move(), Team::Muscovites, "turns were not counted");
+    Ok(())
+}
+
+#[test]
+fn test_record_berserk_end_turn() -> std::io::Result<()> {
+    let variant = setup(Rule::Berserk, Team::Muscovites, BERSERK);
+    let mut game = Game::from_variant(variant.clone());
+    game.move_piece((3, 0), (3, 2)).unwrap();
+
+    let text = Record::new(&game).write(&game);
+    assert!(text.ends_with("1. d11-d9xd8\n"), "turn in progress was not written:\n{}", text);
+    let (_, loaded) = Record::load(&text, Some(variant.clone())).unwrap();
+    assert_eq!(loaded.continuing(), Some((3, 2)), "turn in progress was ended");
+    assert_eq!(loaded.side_to_move(), Team::Muscovites, "turn in progress was passed");
+
+    game.end_turn();
+    let text = Record::new(&game).write(&game);
+    assert!(text.ends_with("1. d11-d9xd8 e\n"), "ended turn was not written:\n{}", text);
+    let (_, loaded) = Record::load(&text, Some(variant)).unwrap();
+    assert_eq!(loaded.history(), game.history(), "ended turn was not replayed");
+    assert_eq!(loaded.continuing(), None, "ended turn goes on");
+    assert_eq!(loaded.side_to_move(), Team::Swedes, "ended turn was not passed");
+    Ok(())
+}
+
+#[test]
+fn test_record_berserk_end_turn_wins() -> std::io::Result<()> {
+    let variant = setup(Rule::Berserk, Team::Muscovites, "
+        + . . . . . . . . . +
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        M . . . . M . . . . .
+        K M . . M S M . . . .
+        M . . . . # . . S M .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . . . . .
+        . . . . . . . c . . .
+        + . . . . . . . . . +
+    ");
+    let mut game = Game::from_variant(variant.clone());
+    game.move_piece((7, 9), (7, 5)).unwrap();
+    assert_eq!(game.continuing(), Some((7, 5)), "commander can not capture again");
+    game.end_turn();
+    assert_eq!(game.result, Some(GameResult::Win(Team::Muscovites)), "ending the turn did not win");
+
+    let text = Record::new(&game).write(&game);
+    assert!(text.ends_with("1. h2-h6xi6#\n"), "win was not marked:\n{}", text);
+    let (loaded, loaded_game) = Record::load(&text, Some(variant)).unwrap();
+    assert_eq!(loaded.result, game.result, "result tag was not kept");
+    assert_eq!(loaded_game.history(), game.history(), "ended turn was not replayed");
+    assert_eq!(loaded_game.result, game.result, "ending the turn did not win on loading");
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use super::piece::Team;
+
+
+/// Where the king has to get to for the Swedes to win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Escape {
+    /// The king has to reach one of the corner squares.
+    Corner,
+    /// The king wins on any square along the edge of the board.
+    Edge,
+}
+
+
+/// The outcome of a repeated position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Repetition {
+    /// The game is drawn when a position occurs for the third time.
+    Draw,
+    /// The side whose move brings about a position for the third time loses,
+    /// as perpetual repetition is forbidden.
+    PerpetualLoss,
+}
+
+
+/// The rules of a variant, independent of its starting layout.
+///
+/// Each `Rule` is a preset of these options, see `Rule::rules`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RuleSet {
+    /// Whether the king takes part in capturing Muscovites.
+    pub king_armed: bool,
+    /// On how many sides the king has to be surrounded to be captured. With
+    /// 2 it is taken between two enemies like any other piece, with 3 or 4
+    /// that many of its neighbours have to be hostile. The edge of the board
+    /// does not count as hostile.
+    pub king_capture_sides: u8,
+    /// Whether the king needs to be surrounded on all four sides while it is
+    /// on or next to the throne, regardless of `king_capture_sides`.
+    pub king_strong_on_throne: bool,
+    pub escape: Escape,
+    /// Whether the throne takes part in capturing Muscovites.
+    pub throne_hostile_to_muscovites: bool,
+    /// Whether the throne takes part in capturing Swedes, including the king.
+    pub throne_hostile_to_swedes: bool,
+    /// Whether the throne is hostile only while the king is not on it.
+    pub throne_hostile_only_empty: bool,
+    /// Whether pieces other than the king may pass through the empty throne.
+    pub throne_passable: bool,
+    pub first_move: Team,
+    pub repetition: Repetition,
+    /// Whether the Swedes win by building an exit fort around the king on
+    /// the edge of the board.
+    pub edge_forts: bool,
+    /// Whether a row of pieces along the edge can be captured as a whole.
+    pub shieldwall: bool,
+    /// Whether the Muscovites win by enclosing all Swedes in an unbroken ring.
+    pub encirclement: bool,
+    /// Whether a piece that captured may move again in the same turn, as
+    /// often as it keeps capturing.
+    pub berserk: bool,
+}
+
+impl RuleSet {
+    /// Whether the throne counts as an enemy of `team`, given whether the
+    /// king is standing on it.
+    pub fn throne_hostile_to(&self, team: Team, occupied: bool) -> bool {
+        let hostile = match team {
+            Team::Muscovites => self.throne_hostile_to_muscovites,
+            Team::Swedes => self.throne_hostile_to_swedes,
+        };
+        hostile && !(occupied && self.throne_hostile_only_empty)
+    }
+}
+
+impl Default for RuleSet {
+    /// The rules shared by the simpler variants: an armed king that is
+    /// captured between two Muscovites unless it stands on or next to the
+    /// throne, escape to the corners and a throne that is hostile to
+    /// everyone while it is empty.
+    fn default() -> Self {
+        Self {
+            king_armed: true,
+            king_capture_sides: 2,
+            king_strong_on_throne: true,
+            escape: Escape::Corner,
+            throne_hostile_to_muscovites: true,
+            throne_hostile_to_swedes: true,
+            throne_hostile_only_empty: true,
+            throne_passable: true,
+            first_move: Team::Muscovites,
+            repetition: Repetition::Draw,
+            edge_forts: false,
+            shieldwall: false,
+            encirclement: false,
+            berserk: false,
+        }
+    }
+}
@@ -3,15 +3,13 @@ use tui::{
     layout::{Alignment, Layout, Constraint, Rect, Direction},
     style::{Color, Style},
     widgets::{Block, BorderType, Borders, Paragraph, Wrap},
-    text::{Text},
+    text::{Span, Spans, Text},
     Frame,
 };
 
-use super::{
-    game::{Coord},
-    piece::{Piece},
-    App,
-};
+use tafl::{Board, Coord, Escape, GameResult, Move, NotatedMove, Piece, Repetition, RuleSet, Square, Status, Team};
+
+use super::App;
 
 
 const PADDING: u16 = 1;
@@ -19,20 +17,33 @@ const PADDING: u16 = 1;
 const CELL_HEIGHT: u16 = 5;
 const CELL_WIDTH: u16 = 9;
 
+/// Width of a cell in compact mode, which uses a single row per cell.
+const COMPACT_CELL_WIDTH: u16 = 3;
+
+const FOOTER_HEIGHT: u16 = 3;
+
 
 pub fn draw <B: Backend> (f: &mut Frame<B>, app: &mut App) {
     let main_block =
         Block::default()
         .borders(Borders::ALL)
         .style(Style::default().bg(Color::Black).fg(Color::Cyan))
-        .title(format!("{}", app.title));
+        .title(app.title.clone());
 
     f.render_widget(main_block, f.size());
 
     let board_size = app.game.board.size;
-    let board_height = CELL_HEIGHT * board_size + 6 * PADDING;
+    let mut board_height = CELL_HEIGHT * board_size + 6 * PADDING;
+
+    // Boards that do not fit the terminal with full cells, in either
+    // direction, are drawn one row per rank.
+    let compact = f.size().height < board_height + FOOTER_HEIGHT + 2
+        || f.size().width < board_width(board_size) + 2;
+    if compact {
+        board_height = board_size + 2;
+    }
 
-    let vert_pad_height = f.size().height.checked_sub(board_height).unwrap_or_default() / 2;
+    let vert_pad_height = f.size().height.saturating_sub(board_height) / 2;
 
     let main_layout =
         Layout::default()
@@ -46,18 +57,25 @@ pub fn draw <B: Backend> (f: &mut Frame<B>, app: &mut App) {
         ])
         .split(f.size());
 
-    if ! app.help {
-        draw_board(f, main_layout[1], app);
+    if app.help {
+        draw_help(f, main_layout[1], &app.game.rules, &app.game.board);
+    } else if compact {
+        draw_board_compact(f, main_layout[1], app);
     } else {
-        draw_help(f, main_layout[1]);
+        draw_board(f, main_layout[1], app);
     }
     draw_footer(f, main_layout[2], app);
 }
 
+/// Width of the board drawn with full cells, including its border.
+fn board_width(board_size: u16) -> u16 {
+    CELL_WIDTH * board_size + 12 * PADDING
+}
+
 fn draw_board <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
     let board_size = app.game.board.size;
-    let board_width = CELL_WIDTH * board_size + 12 * PADDING;
-    let hori_pad_width = rect.width.checked_sub(board_width).unwrap_or_default() / 2;
+    let board_width = board_width(board_size);
+    let hori_pad_width = rect.width.saturating_sub(board_width) / 2;
 
     let outer_board_layout =
         Layout::default()
@@ -114,13 +132,11 @@ fn draw_board <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
         .split(inner_board_layout[0]);
 
     let row_constraints =
-        std::iter::repeat(Constraint::Length(CELL_HEIGHT))
-        .take(board_size as usize)
+        std::iter::repeat_n(Constraint::Length(CELL_HEIGHT), board_size as usize)
         .collect::<Vec<_>>();
 
     let col_constraints =
-        std::iter::repeat(Constraint::Length(CELL_WIDTH))
-        .take(board_size as usize)
+        std::iter::repeat_n(Constraint::Length(CELL_WIDTH), board_size as usize)
         .collect::<Vec<_>>();
 
     let row_rects =
@@ -131,6 +147,10 @@ fn draw_board <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
         .constraints(row_constraints.clone())
         .split(board_layout[0]);
 
+    let targets = app.selected
+        .map(|selected| app.game.legal_moves_from(selected))
+        .unwrap_or_default();
+
     for (r, row_rect) in row_rects.into_iter().enumerate() {
         let col_rects =
             Layout::default()
@@ -141,44 +161,21 @@ fn draw_board <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
             .split(row_rect);
 
         for (c, cell_rect) in col_rects.into_iter().enumerate() {
-            draw_cell(f, cell_rect, app, r, c);
+            draw_cell(f, cell_rect, app, &targets, r, c);
         }
     }
 }
 
-fn draw_cell <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App, row: usize, col: usize) {
+fn draw_cell <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App, targets: &[Move], row: usize, col: usize) {
     let coord: Coord = (col, row);
-    let mid = ((app.game.board.size - 1) / 2) as usize;
     let square = app.game.board.board[col][row];
 
-
-    let style =
-        if app.cursor == coord {
-            Style::default().bg(Color::Black).fg(Color::Green)
-        } else if app.selected == Some((col,row)) {
-            Style::default().bg(Color::Black).fg(Color::Yellow)
-        } else if let Some(selected) = app.selected {
-            let disabled = (selected.0 != col) && (selected.1 != row);
-            if disabled {
-                Style::default().bg(Color::Black).fg(Color::Gray)
-            } else {
-                Style::default().bg(Color::Black).fg(Color::Cyan)
-            }
-        } else {
-            match square.piece {
-                Some(Piece::King) => Style::default().bg(Color::Black).fg(Color::Magenta),
-                Some(Piece::Muscovite) => Style::default().bg(Color::Black).fg(Color::Gray),
-                Some(Piece::Swede) => Style::default().bg(Color::Black).fg(Color::LightRed),
-                None => Style::default().bg(Color::Black).fg(Color::Cyan),
-            }
-        };
+    let style = cell_style(app, targets, square, coord);
 
     let border_type =
-        if app.cursor == coord {
-            BorderType::Thick
-        } else if app.selected == Some((col,row)) {
+        if app.cursor == coord || app.selected == Some((col,row)) {
             BorderType::Thick
-        } else if square.piece != None {
+        } else if square.piece.is_some() {
             BorderType::Double
         } else {
             BorderType::Plain
@@ -199,30 +196,19 @@ fn draw_cell <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App, row: usi
         .constraints(vec![Constraint::Percentage(100)])
         .split(rect);
 
-    if col == mid {
-        if row == mid {
-            let castle_block =
-                Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Double);
-
-            f.render_widget(castle_block, castle_layout[0]);
-        }
-    }
-
-    let size = (app.game.board.size as usize) - 1;
-    let corner =
-        (col == 0 && row == 0)
-        || (col == size && row == 0)
-        || (col == 0 && row == size)
-        || (col == size && row == size);
-    if corner {
-        let corner_block =
+    let status_border = match square.status {
+        Some(Status::Castle) => Some(BorderType::Double),
+        Some(Status::Corner) => Some(BorderType::Plain),
+        Some(Status::Camp) => Some(BorderType::Rounded),
+        None => None,
+    };
+    if let Some(border_type) = status_border {
+        let status_block =
             Block::default()
             .borders(Borders::ALL)
-            .border_type(BorderType::Plain);
+            .border_type(border_type);
 
-            f.render_widget(corner_block, castle_layout[0]);
+        f.render_widget(status_block, castle_layout[0]);
     }
 
     let cell_layout =
@@ -234,7 +220,7 @@ fn draw_cell <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App, row: usi
 
     let p = match square.piece {
         Some(q) => format!("{}", q),
-        _ => format!(" "),
+        _ => " ".to_string(),
     };
 
     let piece =
@@ -246,14 +232,99 @@ fn draw_cell <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App, row: usi
     f.render_widget(piece, cell_layout[0]);
 }
 
-fn draw_help <B: Backend> (f: &mut Frame<B>, rect: Rect) {
+/// Draw the board with one line of text per row, for boards too large to
+/// fit with full cells.
+fn draw_board_compact <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
+    let board_size = app.game.board.size;
+    let board_width = COMPACT_CELL_WIDTH * board_size + 2;
+    let hori_pad_width = rect.width.saturating_sub(board_width) / 2;
+
+    let board_layout =
+        Layout::default()
+        .direction(Direction::Horizontal)
+        .vertical_margin(0)
+        .horizontal_margin(1)
+        .constraints(vec![
+                     Constraint::Min(hori_pad_width),
+                     Constraint::Length(board_width),
+                     Constraint::Min(hori_pad_width),
+        ])
+        .split(rect);
+
+    let targets = app.selected
+        .map(|selected| app.game.legal_moves_from(selected))
+        .unwrap_or_default();
+
+    let rows = (0..board_size as usize)
+        .map(|row| {
+            let cells = (0..board_size as usize)
+                .map(|col| {
+                    let coord: Coord = (col, row);
+                    let square = app.game.board.board[col][row];
+                    let symbol = match (square.piece, square.status) {
+                        (Some(piece), _) => piece.to_string(),
+                        (None, Some(Status::Castle)) => "#".to_string(),
+                        (None, Some(Status::Corner)) => "+".to_string(),
+                        (None, Some(Status::Camp)) => "*".to_string(),
+                        (None, None) => ".".to_string(),
+                    };
+                    let text =
+                        if app.cursor == coord {
+                            format!("[{}]", symbol)
+                        } else if app.selected == Some(coord) {
+                            format!("<{}>", symbol)
+                        } else {
+                            format!(" {} ", symbol)
+                        };
+                    Span::styled(text, cell_style(app, &targets, square, coord))
+                })
+                .collect::<Vec<_>>();
+            Spans::from(cells)
+        })
+        .collect::<Vec<_>>();
+
+    let board =
+        Paragraph::new(rows)
+        .block(Block::default().borders(Borders::ALL).border_type(BorderType::Double))
+        .alignment(Alignment::Left);
+
+    f.render_widget(board, board_layout[1]);
+}
+
+/// The colours of the square at `coord`, depending on the cursor, the
+/// selection and the piece standing on it.
+fn cell_style(app: &App, targets: &[Move], square: Square, coord: Coord) -> Style {
+    if app.cursor == coord {
+        Style::default().bg(Color::Black).fg(Color::Green)
+    } else if app.selected == Some(coord) {
+        Style::default().bg(Color::Black).fg(Color::Yellow)
+    } else if app.selected.is_some() {
+        let disabled = ! targets.iter().any(|m| m.to == coord);
+        if disabled {
+            Style::default().bg(Color::Black).fg(Color::Gray)
+        } else {
+            Style::default().bg(Color::Black).fg(Color::Cyan)
+        }
+    } else {
+        match square.piece {
+            Some(piece) if piece.capabilities().royal => Style::default().bg(Color::Black).fg(Color::Magenta),
+            Some(piece) => match piece.get_team() {
+                Team::Muscovites => Style::default().bg(Color::Black).fg(Color::Gray),
+                Team::Swedes => Style::default().bg(Color::Black).fg(Color::LightRed),
+            },
+            None => Style::default().bg(Color::Black).fg(Color::Cyan),
+        }
+    }
+}
+
+fn draw_help <B: Backend> (f: &mut Frame<B>, rect: Rect, rules: &RuleSet, board: &Board) {
     let help_layout =
         Layout::default()
         .vertical_margin(1)
         .horizontal_margin(1)
         .constraints(vec![
                      Constraint::Min(5),
-                     Constraint::Length(rect.height - 5),
+                     Constraint::Length(rect.height.saturating_sub(5)),
         ])
         .split(rect);
 
@@ -270,30 +341,20 @@ Tafl - A strategy board game.
         .wrap(Wrap {trim: true})
         .alignment(Alignment::Center);
 
-    let help_text = Text::from(r#"
+    let mut help_text = Text::from(r#"
 Controls:
   - Navigation                  |  Up, Down, Left, Right
   - Select,Unselect,Move Piece  |  Space
+  - Undo, Redo Move             |  u, r
+  - End Turn after a Kill       |  e
+  - Save Game                   |  s
   - Help, Return to Game        |  h
   - Quit                        |  Ctrl+c, q
 
 Rules:
-
-The Muscovites (gray, attackers) start the game. A piece can only move horizontally or vertically
-but it can do so any distance unless another piece stands in the way. A piece can also not move
-onto an already occupied square.
-Normal pieces can not move into the center square (castle) or the corner squares, but the King can.
-
-A piece is taken off the board (killed) when it is surrounded on two sides by enemy pieces, unless
-the two enemy pieces were in position first or it is the king inside or directly next to the
-castle. When the king is inside or directly next to the castle, it can only be killed if it is
-surrounded on all four sides.
-A normal piece can also be killed if it is surrounded on one side by a corner square or the castle
-and an enemy piece on the other side.
-
-The goal for the Swedes is to let the king escape onto one of the corner squares. The goal of the
-Muscovites is to block or kill the king.
 "#);
+    help_text.extend(Text::from(rules_help(rules, board)));
+
     let help =
         Paragraph::new(help_text)
         .style(Style::default().fg(Color::Gray))
@@ -305,14 +366,175 @@ Muscovites is to block or kill the king.
     f.render_widget(help, help_layout[1]);
 }
 
+/// Describe the rules of the game being played, one paragraph per aspect,
+/// mentioning the camps and special pieces if the board has any.
+/// Lines are left long, the help paragraph wraps them.
+fn rules_help(rules: &RuleSet, board: &Board) -> String {
+    let squares = || board.board.iter().flatten();
+    let camps = squares().any(|square| square.status == Some(Status::Camp));
+    let commanders = squares().any(|square| matches!(square.piece, Some(Piece::Commander(_))));
+    let knights = squares().any(|square| matches!(square.piece, Some(Piece::Knight(_))));
+    let princes = squares().any(|square| square.piece == Some(Piece::Prince));
+    let corners = rules.escape == Escape::Corner;
+    let mut paragraphs = Vec::new();
+
+    let first = match rules.first_move {
+        Team::Muscovites => "Muscovites (gray, attackers)",
+        Team::Swedes => "Swedes (red, defenders)",
+    };
+    let mut movement = format!(
+        "The {} start the game. A piece can only move horizontally or vertically but it can do so \
+        any distance unless another piece stands in the way. A piece can also not move onto an \
+        already occupied square.\n", first);
+    if corners {
+        movement.push_str("Normal pieces can not move into the center square (castle) or the corner squares, but the King can.");
+    } else {
+        movement.push_str("Normal pieces can not move into the center square (castle), but the King can.");
+    }
+    if !rules.throne_passable {
+        movement.push_str(" They can not pass through the empty castle either.");
+    }
+    paragraphs.push(movement);
+
+    if camps {
+        paragraphs.push(String::from(
+            "The Muscovites start in camps (rounded squares). A Muscovite may move within its camp and \
+            leave it, but no piece may enter a camp from outside or pass through one. A camp also counts \
+            as an enemy piece when killing a piece outside of it."));
+    }
+
+    let mut pieces = Vec::new();
+    if commanders {
+        pieces.push(
+            "Commanders (C for the Swedes, c for the Muscovites) kill and are killed like normal pieces, \
+            but like the king they may stop on the castle and pass through it.");
+    }
+    if knights {
+        pieces.push(
+            "Knights (N for the Swedes, n for the Muscovites) are only killed when they are surrounded on \
+            three sides, the edge of the board does not count.");
+    }
+    if princes {
+        pieces.push(
+            "The prince (P) plays for the Swedes and may stop on the corner squares like the king, but \
+            does not win the game by reaching one.");
+    }
+    if !pieces.is_empty() {
+        paragraphs.push(pieces.join(" "));
+    }
+
+    let mut capture = String::from(
+        "A piece is taken off the board (killed) when it is surrounded on two sides by enemy pieces, \
+        unless the two enemy pieces were in position first.");
+    if !rules.king_armed {
+        capture.push_str(" The king does not take part in killing Muscovites.");
+    }
+    let hostile = match (corners, rules.throne_hostile_to_muscovites, rules.throne_hostile_to_swedes) {
+        (true, true, true) => Some("a corner square or the castle"),
+        (true, true, false) => Some("a corner square or, for Muscovites, the castle"),
+        (true, false, true) => Some("a corner square or, for Swedes, the castle"),
+        (true, false, false) => Some("a corner square"),
+        (false, true, true) => Some("the castle"),
+        (false, true, false) => Some("the castle, if it is a Muscovite"),
+        (false, false, true) => Some("the castle, if it is a Swede"),
+        (false, false, false) => None,
+    };
+    if let Some(hostile) = hostile {
+        capture.push_str(&format!(
+            "\nA normal piece can also be killed if it is surrounded on one side by {} and an enemy piece on the other side.",
+            hostile));
+        if rules.throne_hostile_only_empty {
+            capture.push_str(" The castle only counts while the king is not on it.");
+        }
+    }
+    paragraphs.push(capture);
+
+    let mut king = if rules.king_capture_sides <= 2 {
+        String::from("The king is killed like any other piece, between two enemy pieces.")
+    } else {
+        format!(
+            "The king is only killed when it is surrounded on {} sides, the edge of the board does not count.",
+            rules.king_capture_sides)
+    };
+    let castle_hostile = rules.throne_hostile_to(Team::Swedes, false);
+    if rules.king_strong_on_throne {
+        king.push_str(" When the king stands on the castle, it is only killed by enemy pieces on all four sides.");
+        if castle_hostile {
+            king.push_str(
+                " Directly next to the castle it also has to be surrounded on all four sides, but the \
+                empty castle counts as one of them.");
+        } else {
+            king.push_str(
+                " Directly next to the castle it also has to be surrounded by enemy pieces on all four \
+                sides, the castle does not count.");
+        }
+    } else if rules.king_capture_sides > 2 && castle_hostile {
+        king.push_str(" Next to the castle, the empty castle counts as one of those sides.");
+    }
+    paragraphs.push(king);
+
+    if rules.berserk {
+        paragraphs.push(String::from(
+            "A piece that kills may move again in the same turn, but only to kill again, or for the king \
+            to escape. The turn ends when it has no further kill to make, or when its player ends it."));
+    }
+
+    if rules.shieldwall {
+        paragraphs.push(String::from(
+            "A row of two or more pieces along the edge of the board is killed at once when both ends \
+            are enclosed and every piece of the row has an enemy in front of it. The king survives such a \
+            shieldwall capture."));
+    }
+
+    let mut goal = if corners {
+        String::from("The goal for the Swedes is to let the king escape onto one of the corner squares.")
+    } else {
+        String::from("The goal for the Swedes is to let the king escape onto any square on the edge of the board.")
+    };
+    if rules.edge_forts {
+        goal.push_str(
+            " They also win when the king stands on the edge, is able to move and is enclosed by Swedes \
+            that can not be killed (exit fort).");
+    }
+    goal.push_str(" The goal of the Muscovites is to block or kill the king.");
+    if rules.encirclement {
+        goal.push_str(" They also win by enclosing all Swedes in an unbroken ring.");
+    }
+    goal.push_str(" A side that can not move any of its pieces loses.");
+    paragraphs.push(goal);
+
+    paragraphs.push(String::from(match rules.repetition {
+        Repetition::Draw => "When the same position occurs for the third time the game is a draw.",
+        Repetition::PerpetualLoss => "The side that brings about the same position for the third time loses.",
+    }));
+
+    paragraphs.join("\n\n")
+}
+
 fn draw_footer <B: Backend> (f: &mut Frame<B>, rect: Rect, app: &mut App) {
-    let team = match app.game.turn % 2 {
-        0 => { "Muscovite" },
-        _ => { "Swede" },
+    let status = match app.game.result {
+        Some(GameResult::Win(Team::Muscovites)) => "The Muscovites won.".to_string(),
+        Some(GameResult::Win(Team::Swedes)) => "The Swedes won.".to_string(),
+        Some(GameResult::Draw) => "The game is a draw.".to_string(),
+        None => match app.game.side_to_move() {
+            Team::Muscovites => "It's the Muscovite's turn.".to_string(),
+            Team::Swedes => "It's the Swede's turn.".to_string(),
+        },
     };
 
-    let mut footer_text = Text::from(format!("It's the {}'s turn.\n", team));
-    footer_text.extend(Text::from("help: h | quit: q"));
+    let last_move = app.game.history()
+        .last()
+        .map(|entry| format!(" Last move: {}.", NotatedMove::from(entry).format(app.game.board.size)))
+        .unwrap_or_default();
+    let mut footer_text = Text::from(format!("{}{}\n", status, last_move));
+    if let Some(message) = &app.message {
+        footer_text.extend(Text::from(format!("{}\n", message)));
+    }
+    if app.game.continuing().is_some() {
+        footer_text.extend(Text::from("end turn: e | save: s | help: h | undo: u | redo: r | quit: q"));
+    } else {
+        footer_text.extend(Text::from("save: s | help: h | undo: u | redo: r | quit: q"));
+    }
 
     let footer =
         Paragraph::new(footer_text)
@@ -0,0 +1,345 @@
+//! Variant definitions in a plain text format.
+//!
+//! A variant file holds `key = value` options followed by the starting
+//! position after a `board:` line. Lines starting with `#` before the board
+//! are comments. The options are `name`, `size` and the fields of
+//! [`RuleSet`], any rule left out keeps its default:
+//!
+//! ```text
+//! # Brandubh, the Irish game on 7x7.
+//! name = Brandubh
+//! size = 7
+//! escape = corner
+//! king_capture_sides = 2
+//!
+//! board:
+//! + . . M . . +
+//! . . . M . . .
+//! . . . S . . .
+//! M M S K# S M M
+//! . . . S . . .
+//! . . . M . . .
+//! + . . M . . +
+//! ```
+//!
+//! The board has one line per row, from top to bottom, with one
+//! whitespace-separated token per square. `.` is an empty square, `K`, `M`
+//! and `S` are the king, a Muscovite and a Swede, `#` marks the throne, `+`
+//! a corner and `*` a camp. A piece on a special square is written with
+//! both, as `K#` or `M*`.
+//! Other kinds of pieces use the letters of `Piece::symbol`. The board has to
+//! hold exactly one royal piece, see `Capabilities::royal`.
+
+use std::{error::Error, fmt::{self, Display}, str::FromStr};
+
+use super::board::{Board, Square, Status};
+use super::piece::{Piece, Team};
+use super::rules::{Escape, Repetition, RuleSet};
+
+
+/// A variant: its name, starting position and rules.
+#[derive(Clone)]
+pub struct Variant {
+    pub name: String,
+    pub board: Board,
+    pub rules: RuleSet,
+}
+
+
+/// Why a variant definition could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantError {
+    /// The line the error was found on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for VariantError {}
+
+
+fn error(line: usize, message: impl Into<String>) -> VariantError {
+    VariantError { line, message: message.into() }
+}
+
+fn parse_bool(line: usize, value: &str) -> Result<bool, VariantError> {
+    value.parse().map_err(|_| error(line, format!("expected true or false, found `{}`", value)))
+}
+
+fn parse_option(rules: &mut RuleSet, line: usize, key: &str, value: &str) -> Result<(), VariantError> {
+    match key {
+        "king_armed" => rules.king_armed = parse_bool(line, value)?,
+        "king_capture_sides" => {
+            rules.king_capture_sides = match value.parse() {
+                Ok(sides @ 2..=4) => sides,
+                _ => return Err(error(line, format!("expected 2, 3 or 4 sides, found `{}`", value))),
+            };
+        },
+        "king_strong_on_throne" => rules.king_strong_on_throne = parse_bool(line, value)?,
+        "escape" => {
+            rules.escape = match value {
+                "corner" => Escape::Corner,
+                "edge" => Escape::Edge,
+                _ => return Err(error(line, format!("expected corner or edge, found `{}`", value))),
+            };
+        },
+        "throne_hostile_to_muscovites" => rules.throne_hostile_to_muscovites = parse_bool(line, value)?,
+        "throne_hostile_to_swedes" => rules.throne_hostile_to_swedes = parse_bool(line, value)?,
+        "throne_hostile_only_empty" => rules.throne_hostile_only_empty = parse_bool(line, value)?,
+        "throne_passable" => rules.throne_passable = parse_bool(line, value)?,
+        "first_move" => {
+            rules.first_move = match value {
+                "muscovites" => Team::Muscovites,
+                "swedes" => Team::Swedes,
+                _ => return Err(error(line, format!("expected muscovites or swedes, found `{}`", value))),
+            };
+        },
+        "repetition" => {
+            rules.repetition = match value {
+                "draw" => Repetition::Draw,
+                "perpetual_loss" => Repetition::PerpetualLoss,
+                _ => return Err(error(line, format!("expected draw or perpetual_loss, found `{}`", value))),
+            };
+        },
+        "edge_forts" => rules.edge_forts = parse_bool(line, value)?,
+        "shieldwall" => rules.shieldwall = parse_bool(line, value)?,
+        "encirclement" => rules.encirclement = parse_bool(line, value)?,
+        "berserk" => rules.berserk = parse_bool(line, value)?,
+        _ => return Err(error(line, format!("unknown option `{}`", key))),
+    }
+    Ok(())
+}
+
+/// Parse a single square of the board, like `.`, `M`, `#` or `K#`.
+fn parse_square(token: &str) -> Option<Square> {
+    let mut chars = token.chars().peekable();
+    let piece = chars.peek().copied().and_then(Piece::from_symbol);
+    if piece.is_some() {
+        chars.next();
+    }
+    let status = match (chars.next(), piece) {
+        (Some('#'), _) => Some(Status::Castle),
+        (Some('+'), _) => Some(Status::Corner),
+        (Some('*'), _) => Some(Status::Camp),
+        (Some('.'), None) => None,
+        (None, Some(_)) => None,
+        _ => return None,
+    };
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(Square { piece, status })
+}
+
+/// Write a single square of the board the way `parse_square` reads it.
+pub(crate) fn format_square(square: Square) -> String {
+    let status = match square.status {
+        Some(Status::Castle) => "#",
+        Some(Status::Corner) => "+",
+        Some(Status::Camp) => "*",
+        None => "",
+    };
+    match square.piece {
+        Some(piece) => format!("{}{}", piece.symbol(), status),
+        None if status.is_empty() => ".".to_string(),
+        None => status.to_string(),
+    }
+}
+
+/// Parse `size` rows of `size` squares each from `lines`, skipping empty
+/// ones, and return every row with the line it was found on. Missing rows
+/// are reported on line `last`.
+pub(crate) fn parse_rows<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    size: u16,
+    last: usize,
+) -> Result<Vec<(usize, Vec<Square>)>, VariantError> {
+    let mut rows = Vec::new();
+    let mut lines = lines.filter(|(_, text)| !text.trim().is_empty());
+    while rows.len() < size as usize {
+        let (line, text) = lines
+            .next()
+            .ok_or_else(|| error(last, format!("expected {} rows, found {}", size, rows.len())))?;
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        if tokens.len() != size as usize {
+            return Err(error(line, format!("expected {} squares, found {}", size, tokens.len())));
+        }
+        let row = tokens
+            .into_iter()
+            .map(|token| parse_square(token).ok_or_else(|| error(line, format!("unknown square `{}`", token))))
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.push((line, row));
+    }
+    if let Some((line, _)) = lines.next() {
+        return Err(error(line, format!("expected {} rows, found more", size)));
+    }
+    Ok(rows)
+}
+
+
+impl FromStr for Variant {
+    type Err = VariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut size = None;
+        let mut rules = RuleSet::default();
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        for (line, text) in lines.by_ref() {
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            if text == "board:" {
+                break;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| error(line, "expected `key = value` or `board:`"))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "name" => name = Some(value.to_string()),
+                "size" => {
+                    size = match value.parse::<u16>() {
+                        Ok(size @ 3..=19) => Some(size),
+                        _ => return Err(error(line, format!("expected a size from 3 to 19, found `{}`", value))),
+                    };
+                },
+                _ => parse_option(&mut rules, line, key, value)?,
+            }
+        }
+
+        let last = s.lines().count();
+        let name = name.ok_or_else(|| error(last, "missing option `name`"))?;
+        let size = size.ok_or_else(|| error(last, "missing option `size`"))?;
+
+        let rows = parse_rows(lines, size, last)?;
+        let squares = (0..size as usize)
+            .map(|x| rows.iter().map(|(_, row)| row[x]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        // the game is decided by the royal piece, whichever kind it is
+        let royals = squares
+            .iter()
+            .flatten()
+            .filter(|square| square.piece.is_some_and(|piece| piece.capabilities().royal))
+            .count();
+        if royals != 1 {
+            return Err(error(last, format!("expected one royal piece, found {}", royals)));
+        }
+
+        Ok(Self {
+            name,
+            board: Board::from_squares(squares),
+            rules,
+        })
+    }
+}
+
+
+#[cfg(test)]
+use super::game::Rule;
+
+/// The variant of `rule` starting from the position in `diagram`, written
+/// as the board of a variant file, with `to_move` to make the first move.
+#[cfg(test)]
+pub(crate) fn setup(rule: Rule, to_move: Team, diagram: &str) -> Variant {
+    let variant = rule.variant();
+    Variant {
+        board: Board::from_ascii(rule, diagram).unwrap(),
+        rules: RuleSet { first_move: to_move, ..variant.rules },
+        ..variant
+    }
+}
+
+#[cfg(test)]
+const BRANDUBH: &str = "
+# Brandubh
+name = Brandubh
+size = 7
+escape = edge
+king_capture_sides = 4
+
+board:
+. . . M . . .
+. . . M . . .
+. . . S . . .
+M M S K# S M M
+. . . S . . .
+. . . M . . .
+. . . M . . .
+";
+
+#[test]
+fn test_parse_variant() -> std::io::Result<()> {
+    let variant: Variant = BRANDUBH.parse().unwrap();
+    assert_eq!(variant.name, "Brandubh", "wrong name");
+    assert_eq!(variant.board.size, 7, "wrong size");
+    assert_eq!(variant.rules.escape, Escape::Edge, "escape option was ignored");
+    assert_eq!(variant.rules.king_capture_sides, 4, "king option was ignored");
+    assert!(variant.rules.king_armed, "missing option did not keep its default");
+    assert_eq!(variant.board.get_piece_at((3, 3)), Some(Piece::King), "king is missing");
+    assert_eq!(variant.board.get_status_at((3, 3)), Some(Status::Castle), "throne is missing");
+    assert_eq!(variant.board.get_piece_at((0, 3)), Some(Piece::Muscovite), "Muscovite is missing");
+    assert_eq!(variant.board.get_status_at((0, 0)), None, "corner is special");
+    Ok(())
+}
+
+#[test]
+fn test_variant_first_move() -> std::io::Result<()> {
+    let variant: Variant = BRANDUBH.replace("board:", "first_move = swedes\nboard:").parse().unwrap();
+    let game = super::game::Game::from_variant(variant);
+    assert_eq!(game.side_to_move(), Team::Swedes, "Swedes do not move first");
+    let swedes_only = game.legal_moves()
+        .iter()
+        .all(|m| game.board.get_piece_at(m.from).map(Piece::get_team) == Some(Team::Swedes));
+    assert!(swedes_only, "Muscovites can move first");
+    Ok(())
+}
+
+#[test]
+fn test_parse_square() -> std::io::Result<()> {
+    assert_eq!(parse_square("."), Some(Square::new()));
+    assert_eq!(parse_square("S"), Some(Square { piece: Some(Piece::Swede), status: None }));
+    assert_eq!(parse_square("+"), Some(Square { piece: None, status: Some(Status::Corner) }));
+    assert_eq!(parse_square("K#"), Some(Square { piece: Some(Piece::King), status: Some(Status::Castle) }));
+    assert_eq!(parse_square("M*"), Some(Square { piece: Some(Piece::Muscovite), status: Some(Status::Camp) }));
+    assert_eq!(parse_square("c"), Some(Square { piece: Some(Piece::Commander(Team::Muscovites)), status: None }));
+    assert_eq!(parse_square("K."), None);
+    assert_eq!(parse_square("X"), None);
+    assert_eq!(parse_square("M#+"), None);
+    for token in [".", "S", "+", "K#", "M*", "c"] {
+        assert_eq!(parse_square(token).map(format_square), Some(token.to_string()), "{} does not round trip", token);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_variant_errors() -> std::io::Result<()> {
+    let err = BRANDUBH.replace("escape = edge", "escape = side").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.line), Some(5), "bad option not reported on its line");
+
+    let err = BRANDUBH.replace("king_capture_sides", "king_sides").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.message), Some("unknown option `king_sides`".to_string()));
+
+    let err = BRANDUBH.replace("M M S K# S M M", "M M S K# S M").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.line), Some(12), "short row not reported on its line");
+
+    let err = BRANDUBH.replace("S K# S", "S X# S").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.message), Some("unknown square `X#`".to_string()));
+
+    let err = BRANDUBH.replace("K#", "#").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.message), Some("expected one royal piece, found 0".to_string()));
+
+    let err = BRANDUBH.replace("M M S K# S M M", "M M K K# S M M").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.message), Some("expected one royal piece, found 2".to_string()));
+
+    let err = BRANDUBH.replace("size = 7", "size = 9").parse::<Variant>().err();
+    assert_eq!(err.map(|err| err.message), Some("expected 9 squares, found 7".to_string()));
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Keys for Zobrist hashing of positions.
+//!
+//! The keys are derived from the square and piece with splitmix64 instead of
+//! being drawn at random, so hashes are the same across runs and can be
+//! stored, e.g. in opening books.
+
+use super::game::Coord;
+use super::piece::{Piece, Team};
+
+
+/// Key toggled into the hash when the Swedes are on the move.
+pub const SWEDES_TO_MOVE: u64 = 0x5bd1_e995_7f4a_7c15;
+
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The key of `piece` standing at `at`.
+pub fn key(at: Coord, piece: Piece) -> u64 {
+    let kind = match piece {
+        Piece::King => 0,
+        Piece::Muscovite => 1,
+        Piece::Swede => 2,
+        Piece::Commander(Team::Muscovites) => 3,
+        Piece::Commander(Team::Swedes) => 4,
+        Piece::Knight(Team::Muscovites) => 5,
+        Piece::Knight(Team::Swedes) => 6,
+        Piece::Prince => 7,
+    };
+    splitmix64((((at.0 << 8) | at.1) << 3 | kind) as u64)
+}